@@ -4,16 +4,15 @@ use intcode::Program;
 fn main() {
     let input = fs::read_to_string("input.txt").unwrap();
     let mut program = Program::from(input);
-    let diagnostic_codes = program.run(vec!(5));
+    let diagnostic_codes = program.run(&mut vec!(5));
 
     println!("{:?}", diagnostic_codes);
 }
 
 #[test]
 fn test_program() {
-    let input = fs::read_to_string("test.txt").unwrap();
-    let mut program = Program::from(input);
-    let diagnostic_codes = program.run(vec!(8));
+    let mut program = Program::from(String::from(include_str!("../test.txt")));
+    let diagnostic_codes = program.run(&mut vec!(8));
 
     assert_eq!(*diagnostic_codes.last().unwrap(), 1000);
 }
@@ -1,192 +1,313 @@
-use std::fs;
-use intcode::Program;
+mod permutations;
+mod sweep;
+mod topology;
+mod trace;
 
-struct Amplifier {
-    program: Program,
-    phase_setting: i32
-}
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use intcode::Word;
+use intcode::image::ProgramImage;
+use sweep::{Row, Runner, Sweep};
+use topology::Topology;
+use trace::Format;
 
-impl std::fmt::Debug for Amplifier {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Amplifier <ps: {}>", self.phase_setting)
-    }
+// The best row of a sweep
+#[derive(Debug)]
+struct SweepResult {
+    phase_settings: Vec<Word>,
+    value: Word
 }
 
-impl Amplifier {
-    fn new(program_code: String, phase_setting: i32) -> Self {
-        let mut program = Program::from(program_code);
-        program.append_inputs(&mut vec!(phase_setting));
-        Amplifier { program, phase_setting }
-    }
-
-    fn run(&mut self, input: i32) -> i32 {
-        let mut inputs: Vec<i32> = vec!(input);
-        let outputs = self.program.run(&mut inputs);
-        
-        *outputs.last().unwrap()
+impl SweepResult {
+    fn best(rows: &[Row]) -> Option<Self> {
+        rows.first().map(|row| SweepResult { phase_settings: row.settings.clone(), value: row.value })
     }
+}
 
-    fn run_until_event(&mut self, input: i32) -> intcode::ProgramResult {
-        let mut inputs: Vec<i32> = vec!(input);
-        self.program.append_inputs(&mut inputs);
+fn run_amplifier_array(image: &ProgramImage, phase_settings: &[Word]) -> Result<Word, String> {
+    let run = Topology::chain(phase_settings)?.run(image)?;
+    run.value().ok_or_else(|| String::from("no output"))
+}
 
-        self.program.run_until_event()     
-    }
+fn run_with_feedback(image: &ProgramImage, phase_settings: &[Word]) -> Result<Word, String> {
+    let run = Topology::ring(phase_settings)?.run(image)?;
+    run.value().ok_or_else(|| String::from("no output"))
 }
 
-struct SweepGenerator {
-    possibilities: Vec<i32>,
-    index: usize
+const USAGE: &str = "usage: day-7 [--part n] [--phases a,b,...] [--amplifiers n] [--repeat [--unordered]] [--threads n] [--top n] [--csv file] [--resume file] [--trace table|dot|sparkline] [topology]";
+const PROGRESS: Duration = Duration::from_millis(200);
+
+struct Options {
+    parts: Vec<u32>,
+    phases: Option<Vec<Word>>,
+    amplifiers: Option<usize>,
+    repeat: bool,
+    unordered: bool,
+    threads: usize,
+    top: usize,
+    csv: Option<String>,
+    resume: Option<String>,
+    trace: Option<Format>,
+    topology: Option<String>
 }
 
-impl SweepGenerator {
-    fn new(possibilities: Vec<i32>) -> Self {
-        SweepGenerator { index: 0 , possibilities } 
-    }
+fn number<T: std::str::FromStr>(value: Option<String>, flag: &str) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    value.parse().map_err(|_| format!("{}: invalid value {}", flag, value))
 }
 
-impl Iterator for SweepGenerator {
-    type Item = Vec<i32>;
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut options = Options {
+        parts: vec!(1, 2), phases: None, amplifiers: None, repeat: false, unordered: false, threads, top: 1, csv: None, resume: None, trace: None, topology: None
+    };
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index == 5 * 4 * 3 * 2 { return None; }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--part" => match args.next().as_deref() {
+                Some("1") => options.parts = vec!(1),
+                Some("2") => options.parts = vec!(2),
+                _ => return Err(String::from("--part must be 1 or 2"))
+            },
+            "--phases" => {
+                let value = args.next().ok_or("--phases needs a value")?;
+                let phases = value.split(',').map(|p| p.trim().parse().map_err(|_| format!("--phases: invalid value {}", p)));
+                options.phases = Some(phases.collect::<Result<_, _>>()?);
+            },
+            "--amplifiers" => options.amplifiers = Some(number(args.next(), "--amplifiers")?),
+            "--repeat" => options.repeat = true,
+            "--unordered" => options.unordered = true,
+            "--threads" => options.threads = number(args.next(), "--threads")?,
+            "--top" => options.top = number(args.next(), "--top")?,
+            "--csv" => options.csv = Some(args.next().ok_or("--csv needs a file")?),
+            "--resume" => options.resume = Some(args.next().ok_or("--resume needs a file")?),
+            "--trace" => options.trace = Some(args.next().ok_or("--trace needs a format")?.parse()?),
+            _ if options.topology.is_none() && !arg.starts_with("--") => options.topology = Some(arg),
+            _ => return Err(format!("unexpected argument {}\n{}", arg, USAGE))
+        }
+    }
 
-        let mut possible: Vec<i32> = self.possibilities.clone();
-        let mut current: Vec<i32> = Vec::new();
-        
-        // Save our index and increment it
-        let mut index = self.index;
-        self.index += 1;
+    // Only repeating sweeps can skip orderings
+    if options.unordered && !options.repeat {
+        return Err(String::from("--unordered needs --repeat"));
+    }
 
-        // Iterate through
-        for n in 0..5 {
-            let current_divisor = 5 - n;
-            let current_index = index % current_divisor;
-            current.push(possible.remove(current_index));
-            index = index / current_divisor;
-        }
+    if options.amplifiers == Some(0) {
+        return Err(String::from("--amplifiers must be at least 1"));
+    }
 
-        Some(current)
+    // Result files hold one sweep, so they need a single part
+    if (options.csv.is_some() || options.resume.is_some()) && options.parts.len() > 1 {
+        return Err(String::from("--csv and --resume need --part"));
     }
+
+    Ok(options)
 }
 
-#[derive(Debug)]
-struct SweepResult {
-    phase_settings: Vec<i32>,
-    value: i32
+// Rows already in the resume file are skipped, and every new one is appended as soon as it
+// finishes, so an interrupted sweep picks up where it stopped. The file is rewritten first so
+// a line cut off by the interruption does not run into the next row.
+fn resume_file(path: &str) -> Result<(Vec<Row>, LineWriter<fs::File>), String> {
+    let at_path = |e: String| format!("{}: {}", path, e);
+    let done = if Path::new(path).exists() {
+        sweep::parse_partial_csv(&fs::read_to_string(path).map_err(|e| at_path(e.to_string()))?).map_err(at_path)?
+    } else {
+        Vec::new()
+    };
+
+    fs::write(path, sweep::to_csv(&done)).map_err(|e| at_path(e.to_string()))?;
+    let file = OpenOptions::new().append(true).open(path).map_err(|e| at_path(e.to_string()))?;
+
+    Ok((done, LineWriter::new(file)))
 }
 
-impl SweepResult {
-    fn run_array(code: String, possiblities: Vec<i32>) -> Self {
-        let mut value = -1000;
-        let mut phase_settings: Vec<i32> = Vec::new();
-        let generator = SweepGenerator::new(possiblities);
-    
-        for settings in generator {
-            let result = run_amplifier_array(code.clone(), &settings);
-            if result > value {
-                value = result;
-                phase_settings = settings;
-            }
+fn run(options: Options) -> Result<(), String> {
+    let code = fs::read_to_string("input.txt").map_err(|e| format!("input.txt: {}", e))?;
+    let image: ProgramImage = code.parse().map_err(|e| format!("input.txt: {}", e))?;
+
+    // With a topology file, run that graph once and show every hop
+    if let Some(path) = &options.topology {
+        let run = Topology::load(path)?.run(&image)?;
+        match options.trace {
+            Some(format) => print!("{}", trace::render(&run.trace, format)),
+            None => print!("{}", run)
         }
-    
-        SweepResult { phase_settings, value }
+        return Ok(());
     }
 
-    fn run_feedback_array(code: String, possiblities: Vec<i32>) -> Self {
-        let mut value = -1000;
-        let mut phase_settings: Vec<i32> = Vec::new();
-        let generator = SweepGenerator::new(possiblities);
-    
-        for settings in generator {
-            let result = run_with_feedback(code.clone(), &settings);
-            if result > value {
-                value = result;
-                phase_settings = settings;
+    // Each part sweeps its own phases unless they are given
+    for part in &options.parts {
+        let (default, runner): (Vec<Word>, Runner) = match part {
+            1 => (vec!(0,1,2,3,4), run_amplifier_array),
+            _ => (vec!(5,6,7,8,9), run_with_feedback)
+        };
+        let topology: fn(&[Word]) -> Result<Topology, String> = if *part == 1 { Topology::chain } else { Topology::ring };
+        let phases = options.phases.clone().unwrap_or(default);
+        let sweep = Sweep { amplifiers: options.amplifiers.unwrap_or(phases.len()), phases, repeat: options.repeat, unordered: options.unordered };
+
+        let (done, mut partial) = match &options.resume {
+            Some(path) => resume_file(path).map(|(done, file)| (done, Some(file)))?,
+            None => (Vec::new(), None)
+        };
+
+        let mut reported = Instant::now();
+        let rows = sweep::run(&image, &sweep, runner, done, options.threads, |row, done, total| {
+            if let Some(file) = &mut partial {
+                writeln!(file, "{}", row.to_csv()).map_err(|e| e.to_string())?;
             }
+
+            if reported.elapsed() >= PROGRESS || done == total {
+                eprint!("\rpart {}: {}/{} settings ({:.0}%)", part, done, total, 100.0 * done as f64 / total as f64);
+                reported = Instant::now();
+            }
+            Ok(())
+        });
+        eprintln!();
+        let rows = rows?;
+
+        if let Some(path) = &options.csv {
+            fs::write(path, sweep::to_csv(&rows)).map_err(|e| format!("{}: {}", path, e))?;
         }
-    
-        SweepResult { phase_settings, value }
-    }
-}
 
-fn run_amplifier_array(code: String, phase_settings: &Vec<i32>) -> i32 {
-    let mut value = 0;
-    let mut amplifiers: Vec<Amplifier> = phase_settings
-        .into_iter()
-        .map(|ps| Amplifier::new(code.clone(), *ps))
-        .collect();
+        let best = match SweepResult::best(&rows) {
+            Some(result) => result,
+            None => {
+                println!("part {}: no settings to try", part);
+                continue;
+            }
+        };
+
+        println!("part {}: {} from {:?}", part, best.value, best.phase_settings);
+        for (rank, row) in rows.iter().enumerate().take(options.top).skip(1) {
+            println!("  {}. {} from {:?}", rank + 1, row.value, row.settings);
+        }
 
-    for amplifier in &mut amplifiers {
-        value = amplifier.run(value);
+        // Replays the best setting to show how its signal got there
+        if let Some(format) = options.trace {
+            print!("{}", trace::render(&topology(&best.phase_settings)?.run(&image)?.trace, format));
+        }
     }
 
-    value
+    Ok(())
 }
 
-fn run_with_feedback(code: String, phase_settings: &Vec<i32>) -> i32 {
-    let mut value = 0;
-    let mut amplifiers: Vec<Amplifier> = phase_settings
-        .into_iter()
-        .map(|ps| Amplifier::new(code.clone(), *ps))
-        .collect();
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(run);
 
-    loop {
-        for amplifier in &mut amplifiers {
-            value = match amplifier.run_until_event(value) {
-                intcode::ProgramResult::Output(n) => n,
-                intcode::ProgramResult::Complete => return value
-            };
-        }
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
     }
 }
 
-fn main() {
-    let code = fs::read_to_string("input.txt").unwrap();
-    println!("{:?}", SweepResult::run_feedback_array(code, vec!(5,6,7,8,9)));
+// Sweeps the phases and keeps the best row
+#[cfg(test)]
+fn best(program: intcode::Program, phases: Vec<Word>, runner: Runner) -> SweepResult {
+    let rows = sweep::run(&ProgramImage::from(program), &Sweep::from(phases), runner, Vec::new(), 4, |_, _, _| Ok(())).unwrap();
+    SweepResult::best(&rows).unwrap()
 }
 
 #[test]
 fn test_amp_1() {
-    let phase_settings: Vec<i32> = vec!(4,3,2,1,0);
-    let program = String::from("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
-    let results = SweepResult::run_array(program, vec!(0,1,2,3,4));
+    use intcode::intcode;
+
+    let phase_settings: Vec<Word> = vec!(4,3,2,1,0);
+    let program = intcode![3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0];
+    let results = best(program, vec!(0,1,2,3,4), run_amplifier_array);
     assert_eq!(results.value, 43210);
     assert_eq!(results.phase_settings, phase_settings);
 }
 
 #[test]
 fn test_amp_2() {
-    let phase_settings: Vec<i32> = vec!(0,1,2,3,4);
-    let program = String::from("3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0");
-    let results = SweepResult::run_array(program, vec!(0,1,2,3,4));
+    use intcode::intcode;
+
+    let phase_settings: Vec<Word> = vec!(0,1,2,3,4);
+    let program = intcode![3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0];
+    let results = best(program, vec!(0,1,2,3,4), run_amplifier_array);
     assert_eq!(results.value, 54321);
     assert_eq!(results.phase_settings, phase_settings);
 }
 
 #[test]
 fn test_amp_3() {
-    let phase_settings: Vec<i32> = vec!(1,0,4,3,2);
-    let program = String::from("3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0");
-    let results = SweepResult::run_array(program, vec!(0,1,2,3,4));
+    use intcode::intcode;
+
+    let phase_settings: Vec<Word> = vec!(1,0,4,3,2);
+    let program = intcode![3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0];
+    let results = best(program, vec!(0,1,2,3,4), run_amplifier_array);
     assert_eq!(results.value, 65210);
     assert_eq!(results.phase_settings, phase_settings);
 }
 
 #[test]
 fn test_amp_feedback_1() {
-    let phase_settings: Vec<i32> = vec!(9,8,7,6,5);
-    let program = String::from("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5");
-    let results = SweepResult::run_feedback_array(program, vec!(5,6,7,8,9));
+    use intcode::intcode;
+
+    let phase_settings: Vec<Word> = vec!(9,8,7,6,5);
+    let program = intcode![3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5];
+    let results = best(program, vec!(5,6,7,8,9), run_with_feedback);
     assert_eq!(results.value, 139629729);
     assert_eq!(results.phase_settings, phase_settings);
 }
 
 #[test]
 fn test_amp_feedback_2() {
-    let phase_settings: Vec<i32> = vec!(9,7,8,5,6);
-    let program = String::from("3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10");
-    let results = SweepResult::run_feedback_array(program, vec!(5,6,7,8,9));
+    use intcode::intcode;
+
+    let phase_settings: Vec<Word> = vec!(9,7,8,5,6);
+    let program = intcode![3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10];
+    let results = best(program, vec!(5,6,7,8,9), run_with_feedback);
     assert_eq!(results.value, 18216);
     assert_eq!(results.phase_settings, phase_settings);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_amp_large_signal() {
+    // Like test_amp_1 but each amplifier shifts by 10000, so the answer needs more than 32 bits
+    let program = intcode::intcode! {
+        in([15])
+        in([16])
+        mul([16], 10000, [16])
+        add([16], [15], [16])
+        out([16])
+        hlt()
+        data(0, 0)
+    };
+    let image = ProgramImage::from(program);
+
+    assert_eq!(run_amplifier_array(&image, &[4, 3, 2, 1, 0]), Ok(40003000200010000));
+
+    let rows = sweep::run(&image, &Sweep::from(vec!(0, 1, 2, 3, 4)), run_amplifier_array, Vec::new(), 2, |_, _, _| Ok(())).unwrap();
+    let results = SweepResult::best(&rows).unwrap();
+    assert_eq!(results.value, 40003000200010000);
+    assert_eq!(results.phase_settings, vec!(4, 3, 2, 1, 0));
+    assert_eq!(sweep::parse_csv(&sweep::to_csv(&rows)), Ok(rows));
+}
+
+#[test]
+fn test_amp_feedback_large_signal() {
+    // Like test_amp_feedback_1 but multiplying by 4, so the signal outgrows 32 bits on the way round
+    let program = intcode::intcode! {
+        in([26])
+        add([26], -4, [26])
+        in([27])
+        mul([27], 4, [27])
+        add([27], [26], [27])
+        out([27])
+        add([28], -1, [28])
+        jt([28], 6)
+        hlt()
+        data(0, 0, 5)
+    };
+    let image = ProgramImage::from(program);
+
+    let value = run_with_feedback(&image, &[9, 8, 7, 6, 5]).unwrap();
+    assert!(value > i32::MAX as Word);
+    assert_eq!(value, 1753234165787193);
+}
@@ -0,0 +1,209 @@
+// Both iterators walk index tuples in lexicographic order, so items come out in the order
+// the slice lists them. Their lengths are worked out up front and must fit in a usize,
+// which is far more than could ever be iterated anyway, so anything bigger is an error.
+
+pub struct Permutations<'a, T> {
+    items: &'a [T],
+    indices: Vec<usize>,
+    remaining: usize
+}
+
+pub struct Combinations<'a, T> {
+    items: &'a [T],
+    indices: Vec<usize>,
+    remaining: usize
+}
+
+pub struct Product<'a, T> {
+    items: &'a [T],
+    indices: Vec<usize>,
+    remaining: usize
+}
+
+// Every ordered pick of `k` distinct items, n! / (n - k)! of them
+pub fn permutations<T: Clone>(items: &[T], k: usize) -> Result<Permutations<'_, T>, String> {
+    let n = items.len();
+    let remaining = if k > n { Some(0) } else {
+        (n - k + 1..=n).try_fold(1usize, |count, i| count.checked_mul(i))
+    };
+
+    let remaining = remaining.ok_or_else(|| String::from("too many permutations to count"))?;
+    Ok(Permutations { items, indices: (0..k).collect(), remaining })
+}
+
+// Every ordered pick of `k` items where an item may be picked more than once, n^k of them
+pub fn permutations_with_repetition<T: Clone>(items: &[T], k: usize) -> Result<Product<'_, T>, String> {
+    let remaining = (0..k).try_fold(1usize, |count, _| count.checked_mul(items.len()))
+        .ok_or_else(|| String::from("too many permutations to count"))?;
+
+    Ok(Product { items, indices: vec!(0; k), remaining })
+}
+
+// Every unordered pick of `k` items where an item may be picked more than once, for when
+// the order does not matter. There are (n + k - 1)! / (k! (n - 1)!) of them.
+pub fn combinations_with_repetition<T: Clone>(items: &[T], k: usize) -> Result<Combinations<'_, T>, String> {
+    let n = items.len();
+    let remaining = if n == 0 { Some(if k == 0 { 1 } else { 0 }) } else {
+        (1..=k).try_fold(1usize, |count, i| count.checked_mul(n - 1 + i).map(|c| c / i))
+    };
+
+    let remaining = remaining.ok_or_else(|| String::from("too many combinations to count"))?;
+    Ok(Combinations { items, indices: vec!(0; k), remaining })
+}
+
+impl<'a, T: Clone> Permutations<'a, T> {
+    // The rightmost position that can take a larger unused index gets the next one, and
+    // everything after it restarts from the smallest unused indices
+    fn advance(&mut self) {
+        let n = self.items.len();
+
+        for i in (0..self.indices.len()).rev() {
+            let earlier = &self.indices[..i];
+            if let Some(next) = (self.indices[i] + 1..n).find(|v| !earlier.contains(v)) {
+                self.indices[i] = next;
+
+                let taken = &self.indices[..=i];
+                let free: Vec<usize> = (0..n).filter(|v| !taken.contains(v)).collect();
+                for (slot, value) in self.indices[i + 1..].iter_mut().zip(free) {
+                    *slot = value;
+                }
+                return;
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone> Combinations<'a, T> {
+    // Indices never decrease, so bumping one resets everything after it to match
+    fn advance(&mut self) {
+        let last = self.items.len().saturating_sub(1);
+
+        if let Some(i) = self.indices.iter().rposition(|index| *index < last) {
+            let next = self.indices[i] + 1;
+            self.indices[i..].iter_mut().for_each(|index| *index = next);
+        }
+    }
+}
+
+impl<'a, T: Clone> Product<'a, T> {
+    // Counts in base n, with the last position as the lowest digit
+    fn advance(&mut self) {
+        let n = self.items.len();
+
+        for index in self.indices.iter_mut().rev() {
+            *index += 1;
+            if *index < n { return; }
+            *index = 0;
+        }
+    }
+}
+
+impl<'a, T: Clone> Iterator for Permutations<'a, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+
+        let current = self.indices.iter().map(|i| self.items[*i].clone()).collect();
+        self.remaining -= 1;
+        if self.remaining > 0 { self.advance(); }
+
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Clone> Iterator for Combinations<'a, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+
+        let current = self.indices.iter().map(|i| self.items[*i].clone()).collect();
+        self.remaining -= 1;
+        if self.remaining > 0 { self.advance(); }
+
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Clone> Iterator for Product<'a, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+
+        let current = self.indices.iter().map(|i| self.items[*i].clone()).collect();
+        self.remaining -= 1;
+        if self.remaining > 0 { self.advance(); }
+
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Clone> ExactSizeIterator for Permutations<'a, T> {}
+
+impl<'a, T: Clone> ExactSizeIterator for Combinations<'a, T> {}
+
+impl<'a, T: Clone> ExactSizeIterator for Product<'a, T> {}
+
+#[test]
+fn test_permutations() {
+    let all: Vec<Vec<i32>> = permutations(&[1, 2, 3], 3).unwrap().collect();
+    assert_eq!(all, vec!(vec!(1, 2, 3), vec!(1, 3, 2), vec!(2, 1, 3), vec!(2, 3, 1), vec!(3, 1, 2), vec!(3, 2, 1)));
+
+    let pairs: Vec<Vec<char>> = permutations(&['a', 'b', 'c', 'd'], 2).unwrap().collect();
+    assert_eq!(pairs.len(), 12);
+    assert_eq!(pairs[3], vec!('b', 'a'));
+
+    let mut seven = permutations(&[0, 1, 2, 3, 4, 5, 6], 7).unwrap();
+    assert_eq!(seven.len(), 5040);
+    seven.next();
+    assert_eq!(seven.size_hint(), (5039, Some(5039)));
+    assert_eq!(seven.last(), Some(vec!(6, 5, 4, 3, 2, 1, 0)));
+
+    assert_eq!(permutations(&[1, 2], 3).unwrap().count(), 0);
+    assert_eq!(permutations(&[0; 21], 21).err(), Some(String::from("too many permutations to count")));
+    assert_eq!(permutations::<i32>(&[], 0).unwrap().collect::<Vec<_>>(), vec!(vec!()));
+}
+
+#[test]
+fn test_combinations_with_repetition() {
+    let all: Vec<Vec<i32>> = combinations_with_repetition(&[0, 1, 2], 2).unwrap().collect();
+    assert_eq!(all, vec!(vec!(0, 0), vec!(0, 1), vec!(0, 2), vec!(1, 1), vec!(1, 2), vec!(2, 2)));
+
+    assert_eq!(combinations_with_repetition(&[5, 6, 7, 8, 9], 5).unwrap().len(), 126);
+    assert_eq!(combinations_with_repetition(&[5, 6, 7, 8, 9], 5).unwrap().count(), 126);
+    assert_eq!(combinations_with_repetition::<i32>(&[], 2).unwrap().count(), 0);
+    assert_eq!(combinations_with_repetition(&[0; 100], 100).err(), Some(String::from("too many combinations to count")));
+}
+
+#[test]
+fn test_permutations_with_repetition() {
+    let all: Vec<Vec<i32>> = permutations_with_repetition(&[0, 1], 3).unwrap().collect();
+    assert_eq!(all, vec!(
+        vec!(0, 0, 0), vec!(0, 0, 1), vec!(0, 1, 0), vec!(0, 1, 1),
+        vec!(1, 0, 0), vec!(1, 0, 1), vec!(1, 1, 0), vec!(1, 1, 1)
+    ));
+
+    let mut settings = permutations_with_repetition(&[5, 6, 7, 8, 9], 5).unwrap();
+    assert_eq!(settings.len(), 3125);
+    settings.next();
+    assert_eq!(settings.size_hint(), (3124, Some(3124)));
+    assert_eq!(settings.last(), Some(vec!(9, 9, 9, 9, 9)));
+
+    assert_eq!(permutations_with_repetition::<i32>(&[], 2).unwrap().count(), 0);
+    assert_eq!(permutations_with_repetition(&[1, 2], 0).unwrap().collect::<Vec<_>>(), vec!(vec!()));
+    assert_eq!(permutations_with_repetition(&[0; 30], 30).err(), Some(String::from("too many permutations to count")));
+}
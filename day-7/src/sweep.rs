@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use intcode::Word;
+use intcode::image::ProgramImage;
+use crate::permutations::{combinations_with_repetition, permutations, permutations_with_repetition};
+
+pub type Runner = fn(&ProgramImage, &[Word]) -> Result<Word, String>;
+
+pub const HEADER: &str = "settings,value";
+
+// Which phases to try, on how many amplifiers, and whether two amplifiers may share a phase.
+// Order matters in a chain, since each amplifier feeds the next, but amplifiers that are
+// interchangeable only need each multiset of phases once.
+pub struct Sweep {
+    pub phases: Vec<Word>,
+    pub amplifiers: usize,
+    pub repeat: bool,
+    pub unordered: bool
+}
+
+impl From<Vec<Word>> for Sweep {
+    fn from(phases: Vec<Word>) -> Self {
+        Sweep { amplifiers: phases.len(), phases, repeat: false, unordered: false }
+    }
+}
+
+impl Sweep {
+    pub fn settings(&self) -> Result<Box<dyn ExactSizeIterator<Item = Vec<Word>> + Send + '_>, String> {
+        Ok(match (self.repeat, self.unordered) {
+            (true, true) => Box::new(combinations_with_repetition(&self.phases, self.amplifiers)?),
+            (true, false) => Box::new(permutations_with_repetition(&self.phases, self.amplifiers)?),
+            (false, _) => Box::new(permutations(&self.phases, self.amplifiers)?)
+        })
+    }
+
+    // Whether `settings` would come out of `settings()`, without generating them all
+    pub fn contains(&self, settings: &[Word]) -> bool {
+        let positions: Option<Vec<usize>> = settings.iter().map(|s| self.phases.iter().position(|p| p == s)).collect();
+        let positions = match positions {
+            Some(positions) if positions.len() == self.amplifiers => positions,
+            _ => return false
+        };
+
+        let count = |list: &[Word], value: &Word| list.iter().filter(|v| *v == value).count();
+        match (self.repeat, self.unordered) {
+            (true, true) => positions.windows(2).all(|pair| pair[0] <= pair[1]),
+            (true, false) => true,
+            (false, _) => settings.iter().all(|s| count(settings, s) <= count(&self.phases, s))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub settings: Vec<Word>,
+    pub value: Word
+}
+
+impl Row {
+    // Settings are space separated so the row stays two columns
+    pub fn to_csv(&self) -> String {
+        let settings: Vec<String> = self.settings.iter().map(|s| s.to_string()).collect();
+        format!("{},{}", settings.join(" "), self.value)
+    }
+}
+
+pub fn to_csv(rows: &[Row]) -> String {
+    let mut output = format!("{}\n", HEADER);
+    for row in rows {
+        output.push_str(&row.to_csv());
+        output.push('\n');
+    }
+    output
+}
+
+pub fn parse_csv(contents: &str) -> Result<Vec<Row>, String> {
+    contents.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && line.trim() != HEADER)
+        .map(|(number, line)| {
+            let at_line = |message: String| format!("line {}: {}", number + 1, message);
+            let (settings, value) = line.split_once(',').ok_or_else(|| at_line(String::from("expected settings,value")))?;
+
+            let settings = settings.split_whitespace()
+                .map(|s| s.parse().map_err(|_| at_line(format!("invalid phase '{}'", s))))
+                .collect::<Result<_, _>>()?;
+            let value = value.trim().parse().map_err(|_| at_line(format!("invalid value '{}'", value.trim())))?;
+
+            Ok(Row { settings, value })
+        })
+        .collect()
+}
+
+// For a file that was being appended to when the sweep stopped. Every complete row ends in
+// a newline, so anything after the last one was cut off part way, as was a last line that
+// does not parse; both are dropped rather than failing the whole file.
+pub fn parse_partial_csv(contents: &str) -> Result<Vec<Row>, String> {
+    let contents = &contents[..contents.rfind('\n').map_or(0, |end| end + 1)];
+
+    parse_csv(contents).or_else(|error| {
+        let complete = contents.trim_end().rfind('\n').map_or(0, |end| end + 1);
+        parse_csv(&contents[..complete]).map_err(|_| error)
+    })
+}
+
+// Best first. Ties are broken by settings so the ranking does not depend on which
+// thread finished first.
+pub fn rank(rows: &mut [Row]) {
+    rows.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.settings.cmp(&b.settings)));
+}
+
+// Runs every setting not already in `done` across `threads` workers and returns the whole
+// table, ranked. Settings are generated as the workers take them, so the space is never held
+// in memory. Each new row is handed to `on_row` on the calling thread as soon as it
+// finishes, along with how many rows there are so far and in total, so the caller can
+// report progress and save partial results. Rows in `done` that are not part of this sweep
+// are dropped. The first error stops the sweep.
+pub fn run<F>(image: &ProgramImage, sweep: &Sweep, runner: Runner, done: Vec<Row>, threads: usize, mut on_row: F) -> Result<Vec<Row>, String>
+    where F: FnMut(&Row, usize, usize) -> Result<(), String>
+{
+    let mut finished = HashSet::new();
+    let mut rows: Vec<Row> = done.into_iter()
+        .filter(|row| sweep.contains(&row.settings) && finished.insert(row.settings.clone()))
+        .collect();
+
+    let settings = sweep.settings()?;
+    let total = settings.len();
+    let settings = Mutex::new(settings);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut failure = None;
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (settings, stop, finished) = (&settings, &stop, &finished);
+
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let next = settings.lock().unwrap().find(|s| !finished.contains(s));
+                    let settings = match next {
+                        Some(settings) => settings,
+                        None => break
+                    };
+
+                    let result = runner(image, &settings)
+                        .map_err(|e| format!("{:?}: {}", settings, e))
+                        .map(|value| Row { settings, value });
+                    if sender.send(result).is_err() { break; }
+                }
+            });
+        }
+        drop(sender);
+
+        for result in receiver {
+            let result = result.and_then(|row| {
+                rows.push(row);
+                on_row(&rows[rows.len() - 1], rows.len(), total)
+            });
+
+            if let Err(error) = result {
+                stop.store(true, Ordering::Relaxed);
+                failure = Some(error);
+                break;
+            }
+        }
+    });
+
+    if let Some(error) = failure { return Err(error); }
+
+    rank(&mut rows);
+    Ok(rows)
+}
+
+#[test]
+fn test_sweep_resumes_and_ranks() {
+    // Each amplifier outputs its input times ten plus its phase
+    let image: ProgramImage = "3,15,3,16,1002,16,10,16,1,15,16,15,4,15,99,0,0".parse().unwrap();
+    let runner: Runner = crate::run_amplifier_array;
+    let sweep = Sweep::from(vec!(1, 2, 3));
+
+    let mut seen = Vec::new();
+    let rows = run(&image, &sweep, runner, Vec::new(), 3, |row, done, total| {
+        seen.push((row.settings.clone(), done, total));
+        Ok(())
+    }).unwrap();
+
+    assert_eq!(rows.len(), 6);
+    assert_eq!(rows[0], Row { settings: vec!(3, 2, 1), value: 321 });
+    assert_eq!(seen.last().map(|(_, done, total)| (*done, *total)), Some((6, 6)));
+
+    let partial = parse_csv(&to_csv(&rows[3..])).unwrap();
+    let mut resumed = Vec::new();
+    let rows_again = run(&image, &sweep, runner, partial, 2, |row, _, _| {
+        resumed.push(row.settings.clone());
+        Ok(())
+    }).unwrap();
+
+    assert_eq!(rows_again, rows);
+    assert_eq!(resumed.len(), 3);
+    assert!(resumed.iter().all(|settings| rows[..3].iter().any(|row| row.settings == *settings)));
+
+    // A row from another sweep and a repeated row are both left out of the table
+    let mut foreign = rows[..5].to_vec();
+    foreign.push(Row { settings: vec!(9, 9, 9), value: 999 });
+    foreign.push(rows[0].clone());
+    let rows_again = run(&image, &sweep, runner, foreign, 2, |_, done, total| {
+        assert_eq!((done, total), (6, 6));
+        Ok(())
+    }).unwrap();
+    assert_eq!(rows_again, rows);
+}
+
+#[test]
+fn test_parse_partial_csv() {
+    let complete = "settings,value\n1 2 3,123\n3 2 1,321\n";
+    let rows = vec!(Row { settings: vec!(1, 2, 3), value: 123 }, Row { settings: vec!(3, 2, 1), value: 321 });
+
+    assert_eq!(parse_partial_csv(complete), Ok(rows.clone()));
+    assert_eq!(parse_partial_csv(&format!("{}2 1", complete)), Ok(rows.clone()));
+    assert_eq!(parse_partial_csv(&format!("{}2 1 3,21", complete)), Ok(rows.clone()));
+    assert_eq!(parse_partial_csv(&format!("{}2 1 3,\n", complete)), Ok(rows));
+    assert_eq!(parse_partial_csv("settings,value\n1 x,1\n3 2 1,321\n"), Err(String::from("line 2: invalid phase 'x'")));
+}
+
+#[test]
+fn test_repeating_sweep_tries_every_order() {
+    let mut sweep = Sweep { phases: vec!(0, 1), amplifiers: 3, repeat: true, unordered: false };
+    let settings: Vec<Vec<Word>> = sweep.settings().unwrap().collect();
+
+    assert_eq!(settings.len(), 8);
+    assert!(settings.contains(&vec!(1, 0, 0)) && settings.contains(&vec!(0, 0, 1)));
+
+    sweep.unordered = true;
+    assert_eq!(sweep.settings().unwrap().count(), 4);
+}
+
+#[test]
+fn test_sweep_too_large_to_count() {
+    let image: ProgramImage = "99".parse().unwrap();
+    let sweep = Sweep { phases: (0..30).collect(), amplifiers: 30, repeat: true, unordered: false };
+
+    assert_eq!(sweep.settings().err(), Some(String::from("too many permutations to count")));
+    assert_eq!(run(&image, &sweep, crate::run_amplifier_array, Vec::new(), 1, |_, _, _| Ok(())), Err(String::from("too many permutations to count")));
+}
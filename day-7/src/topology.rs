@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use intcode::{ProgramResult, Word};
+use intcode::image::ProgramImage;
+
+// Far more than any amplifier needs, but a node stuck in a loop still fails the run
+const STEP_LIMIT: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combine {
+    Sum,
+    Product,
+    Max,
+    Min
+}
+
+impl Combine {
+    fn apply(&self, values: &[Word]) -> Result<Word, String> {
+        let mut values = values.iter().copied();
+
+        let combined = match self {
+            Combine::Sum => values.try_fold(0, Word::checked_add),
+            Combine::Product => values.try_fold(1, Word::checked_mul),
+            Combine::Max => Some(values.max().unwrap_or(0)),
+            Combine::Min => Some(values.min().unwrap_or(0))
+        };
+
+        combined.ok_or_else(|| String::from("combined input overflows"))
+    }
+}
+
+impl FromStr for Combine {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "sum" => Ok(Combine::Sum),
+            "product" => Ok(Combine::Product),
+            "max" => Ok(Combine::Max),
+            "min" => Ok(Combine::Min),
+            other => Err(format!("unknown combine '{}'", other))
+        }
+    }
+}
+
+// Initial inputs are fed before anything arriving on an edge, so a phase is just the first one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub inputs: Vec<Word>,
+    pub combine: Combine
+}
+
+impl Node {
+    pub fn new(name: &str, inputs: Vec<Word>) -> Self {
+        Node { name: String::from(name), inputs, combine: Combine::Sum }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<(usize, usize)>,
+    pub outputs: Vec<usize>
+}
+
+// `iteration` counts the outputs of the emitting node from 1, so in a loop it is the lap.
+// A node with no outgoing edge still shows up, with no `to`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop {
+    pub iteration: usize,
+    pub from: String,
+    pub to: Option<String>,
+    pub value: Word
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub trace: Vec<Hop>,
+    pub outputs: Vec<(String, Option<Word>)>
+}
+
+impl Run {
+    // The last value from the first output node
+    pub fn value(&self) -> Option<Word> {
+        self.outputs.first().and_then(|(_, value)| *value)
+    }
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for hop in &self.trace {
+            match &hop.to {
+                Some(to) => writeln!(f, "{} -> {}: {}", hop.from, to, hop.value)?,
+                None => writeln!(f, "{}: {}", hop.from, hop.value)?
+            }
+        }
+
+        for (name, value) in &self.outputs {
+            match value {
+                Some(value) => writeln!(f, "{} = {}", name, value)?,
+                None => writeln!(f, "{} produced nothing", name)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A..Z, then AA, AB and so on like spreadsheet columns
+fn amplifier_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut rest = index + 1;
+
+    while rest > 0 {
+        rest -= 1;
+        name.push((b'A' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+
+    name.iter().rev().collect()
+}
+
+fn values(text: &str) -> Result<Vec<Word>, String> {
+    text.split(',')
+        .map(|item| item.trim().parse().map_err(|_| format!("invalid number '{}'", item)))
+        .collect()
+}
+
+impl Topology {
+    // Part one: each amplifier feeds the next and the last one is the answer
+    pub fn chain(phases: &[Word]) -> Result<Self, String> {
+        if phases.is_empty() { return Err(String::from("need at least one amplifier")); }
+
+        let nodes: Vec<Node> = phases.iter()
+            .enumerate()
+            .map(|(index, phase)| {
+                let inputs = if index == 0 { vec!(*phase, 0) } else { vec!(*phase) };
+                Node::new(&amplifier_name(index), inputs)
+            })
+            .collect();
+
+        let edges = (1..nodes.len()).map(|index| (index - 1, index)).collect();
+        let outputs = vec!(nodes.len() - 1);
+
+        Ok(Topology { nodes, edges, outputs })
+    }
+
+    // Part two: the same chain with the last amplifier feeding back into the first
+    pub fn ring(phases: &[Word]) -> Result<Self, String> {
+        let mut topology = Topology::chain(phases)?;
+        topology.edges.push((phases.len() - 1, 0));
+        Ok(topology)
+    }
+
+    // One statement per line, `#` starts a comment:
+    //   node <name> [phase=<n>] [input=<n,...>] [combine=sum|product|max|min]
+    //   edge <name> -> <name>,<name> -> ...
+    //   output <name>
+    // An edge line may chain several hops, and a comma fans out to or in from each name.
+    // Without an output line, every node with no outgoing edge is an output.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut edges = Vec::new();
+        let mut outputs = Vec::new();
+
+        for (number, line) in contents.lines().enumerate() {
+            let at_line = |message: String| format!("line {}: {}", number + 1, message);
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+
+            let find = |name: &str| nodes.iter().position(|node| node.name == name)
+                .ok_or_else(|| at_line(format!("unknown node '{}'", name)));
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+            match keyword {
+                "node" => {
+                    let mut words = rest.split_whitespace();
+                    let name = words.next().ok_or_else(|| at_line(String::from("node needs a name")))?;
+                    if find(name).is_ok() { return Err(at_line(format!("node '{}' is already defined", name))); }
+
+                    let mut node = Node::new(name, Vec::new());
+                    let mut phase = Vec::new();
+
+                    for word in words {
+                        let (key, value) = word.split_once('=').ok_or_else(|| at_line(format!("expected key=value, found '{}'", word)))?;
+                        match key {
+                            "phase" => phase = values(value).map_err(at_line)?,
+                            "input" => node.inputs = values(value).map_err(at_line)?,
+                            "combine" => node.combine = value.parse().map_err(at_line)?,
+                            other => return Err(at_line(format!("unknown key '{}'", other)))
+                        }
+                    }
+
+                    phase.append(&mut node.inputs);
+                    node.inputs = phase;
+                    nodes.push(node);
+                },
+                "edge" => {
+                    let stages = rest.split("->")
+                        .map(|stage| stage.split(',').map(|name| find(name.trim())).collect::<Result<Vec<_>, _>>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if stages.len() < 2 { return Err(at_line(String::from("expected 'edge <from> -> <to>'"))); }
+
+                    for pair in stages.windows(2) {
+                        for from in &pair[0] {
+                            edges.extend(pair[1].iter().map(|to| (*from, *to)));
+                        }
+                    }
+                },
+                "output" => outputs.push(find(rest.trim())?),
+                other => return Err(at_line(format!("unknown statement '{}'", other)))
+            }
+        }
+
+        if outputs.is_empty() {
+            outputs = (0..nodes.len()).filter(|node| edges.iter().all(|(from, _)| from != node)).collect();
+        }
+        if outputs.is_empty() { return Err(String::from("no output node, add an output line")); }
+
+        Ok(Topology { nodes, edges, outputs })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Topology::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Each node runs until it halts or needs input. A node with several incoming edges
+    // waits for a value on every one of them and reads their combination. The run ends
+    // when every node has halted, and fails if the rest are all stuck waiting or one runs
+    // past STEP_LIMIT.
+    pub fn run(&self, image: &ProgramImage) -> Result<Run, String> {
+        let mut programs: Vec<_> = self.nodes.iter()
+            .map(|node| {
+                let mut program = image.instantiate();
+                program.append_inputs(&mut node.inputs.clone());
+                program
+            })
+            .collect();
+
+        let mut queues: Vec<VecDeque<Word>> = vec!(VecDeque::new(); self.edges.len());
+        let mut halted = vec!(false; self.nodes.len());
+        let mut last = vec!(None; self.nodes.len());
+        let mut emitted = vec!(0; self.nodes.len());
+        let mut trace = Vec::new();
+
+        while halted.iter().any(|h| !h) {
+            let mut progress = false;
+
+            for (index, program) in programs.iter_mut().enumerate() {
+                while !halted[index] {
+                    if program.awaiting_input() {
+                        let incoming: Vec<usize> = (0..self.edges.len()).filter(|e| self.edges[*e].1 == index).collect();
+                        if incoming.is_empty() || incoming.iter().any(|e| queues[*e].is_empty()) { break; }
+
+                        let values: Vec<Word> = incoming.iter().filter_map(|e| queues[*e].pop_front()).collect();
+                        let value = self.nodes[index].combine.apply(&values).map_err(|e| format!("{}: {}", self.nodes[index].name, e))?;
+                        program.append_inputs(&mut vec!(value));
+                    }
+
+                    if program.steps() >= STEP_LIMIT {
+                        return Err(format!("{}: did not halt within {} steps", self.nodes[index].name, STEP_LIMIT));
+                    }
+
+                    progress = true;
+
+                    match program.try_step() {
+                        Ok(Some(ProgramResult::Output(value))) => {
+                            last[index] = Some(value);
+                            emitted[index] += 1;
+                            let hop = |to: Option<usize>| Hop {
+                                iteration: emitted[index],
+                                from: self.nodes[index].name.clone(),
+                                to: to.map(|to| self.nodes[to].name.clone()),
+                                value
+                            };
+
+                            let outgoing: Vec<(usize, usize)> = self.edges.iter().enumerate()
+                                .filter(|(_, (from, _))| *from == index)
+                                .map(|(edge, (_, to))| (edge, *to))
+                                .collect();
+                            if outgoing.is_empty() { trace.push(hop(None)); }
+
+                            for (edge, to) in outgoing {
+                                queues[edge].push_back(value);
+                                trace.push(hop(Some(to)));
+                            }
+                        },
+                        Ok(Some(ProgramResult::Complete)) => halted[index] = true,
+                        Ok(None) => (),
+                        Err(error) => return Err(format!("{}: {}", self.nodes[index].name, error))
+                    }
+                }
+            }
+
+            if !progress {
+                let waiting: Vec<&str> = (0..self.nodes.len()).filter(|n| !halted[*n]).map(|n| self.nodes[n].name.as_str()).collect();
+                return Err(format!("deadlock, waiting for input: {}", waiting.join(", ")));
+            }
+        }
+
+        let outputs = self.outputs.iter().map(|n| (self.nodes[*n].name.clone(), last[*n])).collect();
+        Ok(Run { trace, outputs })
+    }
+}
+
+#[test]
+fn test_chain_and_ring() {
+    let image: ProgramImage = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".parse().unwrap();
+    let run = Topology::chain(&[4, 3, 2, 1, 0]).unwrap().run(&image).unwrap();
+    assert_eq!(run.value(), Some(43210));
+    assert_eq!(run.trace[0], Hop { iteration: 1, from: String::from("A"), to: Some(String::from("B")), value: 4 });
+    assert_eq!(run.trace[4], Hop { iteration: 1, from: String::from("E"), to: None, value: 43210 });
+
+    let image: ProgramImage = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5".parse().unwrap();
+    let ring = Topology::parse(include_str!("../feedback.txt")).unwrap();
+    assert_eq!(ring, Topology::ring(&[9, 8, 7, 6, 5]).unwrap());
+    assert_eq!(ring.run(&image).unwrap().value(), Some(139629729));
+
+    assert_eq!(Topology::chain(&[]), Err(String::from("need at least one amplifier")));
+    let names: Vec<String> = Topology::ring(&[0; 30]).unwrap().nodes.into_iter().map(|node| node.name).collect();
+    assert_eq!(names[..2], ["A", "B"]);
+    assert_eq!(names[25..28], ["Z", "AA", "AB"]);
+}
+
+#[test]
+fn test_fan_out_and_fan_in() {
+    // Each node adds one to whatever it reads
+    let image: ProgramImage = "3,0,1001,0,1,0,4,0,99".parse().unwrap();
+    let topology = Topology::parse("
+        node source input=1
+        node left
+        node right input=10   # reads its own input and never the edge
+        node sink combine=product
+        edge source -> left,right -> sink
+    ").unwrap();
+
+    let run = topology.run(&image).unwrap();
+    assert_eq!(run.outputs, vec!((String::from("sink"), Some(3 * 11 + 1))));
+    assert_eq!(run.trace.len(), 5);
+
+    let topology = Topology::parse("
+        node left input=9223372036854775806
+        node right input=1
+        node sink
+        edge left,right -> sink
+    ").unwrap();
+    assert_eq!(topology.run(&image), Err(String::from("sink: combined input overflows")));
+
+    assert_eq!(Topology::parse("node a\nedge a -> b"), Err(String::from("line 2: unknown node 'b'")));
+    assert_eq!(Topology::parse("node a\nedge a -> a"), Err(String::from("no output node, add an output line")));
+}
+
+#[test]
+fn test_node_that_never_halts() {
+    let image: ProgramImage = "3,0,1105,1,2".parse().unwrap();
+    let run = Topology::chain(&[1, 2]).unwrap().run(&image);
+
+    assert_eq!(run, Err(format!("A: did not halt within {} steps", STEP_LIMIT)));
+}
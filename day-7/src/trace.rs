@@ -0,0 +1,205 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use intcode::Word;
+use crate::topology::Hop;
+
+const BARS: [char; 8] = ['_', '.', '-', '=', '+', '*', '#', '@'];
+
+// Where the dot diagram sends values that leave the graph. Node names cannot hold spaces.
+const SINK: &str = "(no edge)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Dot,
+    Sparkline
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "table" => Ok(Format::Table),
+            "dot" => Ok(Format::Dot),
+            "sparkline" => Ok(Format::Sparkline),
+            other => Err(format!("unknown trace format '{}', expected table, dot or sparkline", other))
+        }
+    }
+}
+
+pub fn render(trace: &[Hop], format: Format) -> String {
+    match format {
+        Format::Table => table(trace),
+        Format::Dot => dot(trace),
+        Format::Sparkline => sparklines(trace)
+    }
+}
+
+// Every node in the order it first sends or receives
+fn names(trace: &[Hop]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for hop in trace {
+        for name in [Some(hop.from.as_str()), hop.to.as_deref()].iter().flatten() {
+            if !names.contains(name) { names.push(*name); }
+        }
+    }
+    names
+}
+
+// What a node emitted, one value per iteration however many edges it went out on
+fn emitted(trace: &[Hop], name: &str) -> Vec<Word> {
+    let mut values = Vec::new();
+    for hop in trace.iter().filter(|hop| hop.from == name) {
+        if hop.iteration > values.len() { values.push(hop.value); }
+    }
+    values
+}
+
+// One row per loop and one column per node, so a node that went wrong shows up as the
+// first odd cell in its column
+pub fn table(trace: &[Hop]) -> String {
+    let names = names(trace);
+    let columns: Vec<Vec<Word>> = names.iter().map(|name| emitted(trace, name)).collect();
+    let loops = columns.iter().map(|values| values.len()).max().unwrap_or(0);
+
+    let cell = |column: &Vec<Word>, row: usize| column.get(row).map_or(String::from("-"), |value| value.to_string());
+    let widths: Vec<usize> = names.iter()
+        .zip(&columns)
+        .map(|(name, column)| (0..loops).map(|row| cell(column, row).len()).chain([name.len()]).max().unwrap_or(0))
+        .collect();
+
+    let mut output = String::from("loop");
+    for (name, width) in names.iter().zip(&widths) {
+        write!(output, "  {:>width$}", name, width = width).unwrap();
+    }
+    output.push('\n');
+
+    for row in 0..loops {
+        write!(output, "{:>4}", row + 1).unwrap();
+        for (column, width) in columns.iter().zip(&widths) {
+            write!(output, "  {:>width$}", cell(column, row), width = width).unwrap();
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+// A sequence diagram: each node gets a dashed lifeline running down the page, and hop
+// `n` is an arrow between the lifelines on row `n`
+pub fn dot(trace: &[Hop]) -> String {
+    let mut names = names(trace);
+    if trace.iter().any(|hop| hop.to.is_none()) { names.push(SINK); }
+
+    let mut output = String::from("digraph trace {\n    node [shape=point];\n    edge [arrowhead=none, style=dashed];\n");
+
+    for name in &names {
+        writeln!(output, "    {} [shape=box];", point(name, 0)).unwrap();
+    }
+    for row in 0..=trace.len() {
+        let points: Vec<String> = names.iter().map(|name| point(name, row)).collect();
+        writeln!(output, "    {{ rank=same; {}; }}", points.join("; ")).unwrap();
+    }
+    for name in &names {
+        let lifeline: Vec<String> = (0..=trace.len()).map(|row| point(name, row)).collect();
+        writeln!(output, "    {};", lifeline.join(" -> ")).unwrap();
+    }
+
+    for (row, hop) in trace.iter().enumerate() {
+        writeln!(output, "    {} -> {} [style=solid, arrowhead=normal, constraint=false, label=\"loop {}: {}\"];",
+            point(&hop.from, row + 1), point(hop.to.as_deref().unwrap_or(SINK), row + 1), hop.iteration, hop.value).unwrap();
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+// Row 0 is the box with the node's name. Names come from topology files, so quotes and
+// backslashes in them are escaped.
+fn point(name: &str, row: usize) -> String {
+    let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+    if row == 0 { format!("\"{}\"", name) } else { format!("\"{} {}\"", name, row) }
+}
+
+// What each node emitted, between the smallest and largest value it sent. Values in a
+// feedback loop tend to grow geometrically, so the scale is logarithmic.
+pub fn sparklines(trace: &[Hop]) -> String {
+    let names = names(trace);
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let mut output = String::new();
+
+    for name in names {
+        let values = emitted(trace, name);
+        let (min, max) = match (values.iter().min(), values.iter().max()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => {
+                writeln!(output, "{:<width$}  (nothing sent)", name, width = width).unwrap();
+                continue;
+            }
+        };
+
+        let line: String = values.iter().map(|value| bar(*value, min, max)).collect();
+        writeln!(output, "{:<width$}  {}  {}..{}", name, line, min, max, width = width).unwrap();
+    }
+
+    output
+}
+
+// Keeps the sign, so negative values still sit below positive ones
+fn magnitude(value: Word) -> f64 {
+    value.signum() as f64 * (value.unsigned_abs() as f64).ln_1p()
+}
+
+fn bar(value: Word, min: Word, max: Word) -> char {
+    if min == max { return BARS[BARS.len() / 2]; }
+
+    let (value, min, max) = (magnitude(value), magnitude(min), magnitude(max));
+    let scaled = ((value - min) / (max - min) * (BARS.len() - 1) as f64).round() as usize;
+    BARS[scaled.min(BARS.len() - 1)]
+}
+
+#[test]
+fn test_feedback_trace() {
+    use intcode::image::ProgramImage;
+    use crate::topology::Topology;
+
+    let image: ProgramImage = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5".parse().unwrap();
+    let run = Topology::ring(&[9, 8, 7, 6, 5]).unwrap().run(&image).unwrap();
+
+    let table = table(&run.trace);
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(rows.len(), 6);
+    assert_eq!(rows[0], "loop        A         B         C         D          E");
+    assert_eq!(rows[1], "   1        5        14        31        64        129");
+    assert_eq!(rows[5].split_whitespace().last(), Some("139629729"));
+
+    let lines = sparklines(&run.trace);
+    assert_eq!(lines.lines().last(), Some("E  _-+*@  129..139629729"));
+
+    let graph = dot(&run.trace);
+    assert!(graph.contains("\"E 25\" -> \"A 25\" [style=solid, arrowhead=normal, constraint=false, label=\"loop 5: 139629729\"];"));
+    assert_eq!(graph.matches("rank=same").count(), run.trace.len() + 1);
+}
+
+#[test]
+fn test_chain_trace_shows_final_output() {
+    use intcode::image::ProgramImage;
+    use crate::topology::Topology;
+
+    let image: ProgramImage = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".parse().unwrap();
+    let run = Topology::chain(&[4, 3, 2, 1, 0]).unwrap().run(&image).unwrap();
+
+    assert_eq!(table(&run.trace).lines().last(), Some("   1  4  43  432  4321  43210"));
+    assert_eq!(sparklines(&run.trace).lines().last(), Some("E  +  43210..43210"));
+    assert!(dot(&run.trace).contains("\"E 5\" -> \"(no edge) 5\" [style=solid, arrowhead=normal, constraint=false, label=\"loop 1: 43210\"];"));
+}
+
+#[test]
+fn test_dot_escapes_names() {
+    let trace = vec!(Hop { iteration: 1, from: String::from("a\"b\\"), to: None, value: 1 });
+    let graph = dot(&trace);
+
+    assert!(graph.contains("    \"a\\\"b\\\\\" [shape=box];\n"));
+    assert!(graph.contains("\"a\\\"b\\\\ 1\" -> \"(no edge) 1\""));
+}
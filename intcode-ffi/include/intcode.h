@@ -0,0 +1,101 @@
+#ifndef INTCODE_H
+#define INTCODE_H
+
+/* Generated by cbindgen from src/lib.rs; regenerate with `cbindgen --config cbindgen.toml -o include/intcode.h` */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum IntcodeStatus {
+  IntcodeStatus_Output = 0,
+  IntcodeStatus_Complete = 1,
+  IntcodeStatus_NeedsInput = 2,
+  IntcodeStatus_Error = -1,
+} IntcodeStatus;
+
+typedef struct IntcodeMachine IntcodeMachine;
+
+/**
+ * Copies `len` words into a new machine. Returns null if `words` is null.
+ *
+ * # Safety
+ *
+ * `words` must point to `len` readable words.
+ */
+struct IntcodeMachine *intcode_new(const int64_t *words, size_t len);
+
+/**
+ * # Safety
+ *
+ * `machine` must come from `intcode_new` and must not be used afterwards.
+ */
+void intcode_free(struct IntcodeMachine *machine);
+
+/**
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new`.
+ */
+void intcode_push_input(struct IntcodeMachine *machine, int64_t value);
+
+/**
+ * Runs until the program outputs a value, halts, or needs input that has not been pushed.
+ * A faulting program reports `Error` and stays at the faulting instruction; the reason is
+ * available from `intcode_error_message`.
+ *
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new`.
+ */
+enum IntcodeStatus intcode_run_until_event(struct IntcodeMachine *machine);
+
+/**
+ * Copies the message for the last `Error` status into `buffer`, truncated to fit and
+ * always NUL terminated when `len` is not zero. Returns the full length of the message
+ * without the NUL, or 0 if the last run did not fail.
+ *
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new` and `buffer` must point to `len`
+ * writable bytes.
+ */
+size_t intcode_error_message(const struct IntcodeMachine *machine, char *buffer, size_t len);
+
+/**
+ * The value from the last `Output` status.
+ *
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new`.
+ */
+int64_t intcode_output(const struct IntcodeMachine *machine);
+
+/**
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new`.
+ */
+size_t intcode_memory_size(const struct IntcodeMachine *machine);
+
+/**
+ * Returns false if `address` is out of range.
+ *
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new` and `value` must be writable.
+ */
+bool intcode_read_memory(const struct IntcodeMachine *machine, size_t address, int64_t *value);
+
+/**
+ * Returns false if `address` is out of range.
+ *
+ * # Safety
+ *
+ * `machine` must be a live pointer from `intcode_new`.
+ */
+bool intcode_write_memory(struct IntcodeMachine *machine, size_t address, int64_t value);
+
+#endif  /* INTCODE_H */
@@ -0,0 +1,205 @@
+use std::os::raw::c_char;
+use std::slice;
+use intcode::{Program, ProgramError, ProgramResult};
+
+pub struct IntcodeMachine {
+    program: Program,
+    output: i64,
+    error: Option<ProgramError>
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub enum IntcodeStatus {
+    Output = 0,
+    Complete = 1,
+    NeedsInput = 2,
+    Error = -1
+}
+
+/// Copies `len` words into a new machine. Returns null if `words` is null.
+///
+/// # Safety
+///
+/// `words` must point to `len` readable words.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_new(words: *const i64, len: usize) -> *mut IntcodeMachine {
+    if words.is_null() { return std::ptr::null_mut(); }
+
+    let image = slice::from_raw_parts(words, len).to_vec();
+    Box::into_raw(Box::new(IntcodeMachine { program: Program::from(image), output: 0, error: None }))
+}
+
+/// # Safety
+///
+/// `machine` must come from `intcode_new` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_free(machine: *mut IntcodeMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_push_input(machine: *mut IntcodeMachine, value: i64) {
+    let machine = &mut *machine;
+    machine.program.append_inputs(&mut vec!(value));
+}
+
+/// Runs until the program outputs a value, halts, or needs input that has not been pushed.
+/// A faulting program reports `Error` and stays at the faulting instruction; the reason is
+/// available from `intcode_error_message`.
+///
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run_until_event(machine: *mut IntcodeMachine) -> IntcodeStatus {
+    let machine = &mut *machine;
+    machine.error = None;
+
+    loop {
+        if machine.program.awaiting_input() {
+            return IntcodeStatus::NeedsInput;
+        }
+
+        match machine.program.try_step() {
+            Ok(Some(ProgramResult::Output(value))) => {
+                machine.output = value;
+                return IntcodeStatus::Output;
+            },
+            Ok(Some(ProgramResult::Complete)) => return IntcodeStatus::Complete,
+            Ok(None) => (),
+            Err(error) => {
+                machine.error = Some(error);
+                return IntcodeStatus::Error;
+            }
+        }
+    }
+}
+
+/// Copies the message for the last `Error` status into `buffer`, truncated to fit and
+/// always NUL terminated when `len` is not zero. Returns the full length of the message
+/// without the NUL, or 0 if the last run did not fail.
+///
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new` and `buffer` must point to `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_error_message(machine: *const IntcodeMachine, buffer: *mut c_char, len: usize) -> usize {
+    let machine = &*machine;
+    let message = match &machine.error {
+        Some(error) => error.to_string(),
+        None => return 0
+    };
+
+    if !buffer.is_null() && len > 0 {
+        let copied = message.len().min(len - 1);
+        std::ptr::copy_nonoverlapping(message.as_ptr() as *const c_char, buffer, copied);
+        *buffer.add(copied) = 0;
+    }
+
+    message.len()
+}
+
+/// The value from the last `Output` status.
+///
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_output(machine: *const IntcodeMachine) -> i64 {
+    let machine = &*machine;
+    machine.output
+}
+
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_memory_size(machine: *const IntcodeMachine) -> usize {
+    let machine = &*machine;
+    machine.program.memory().len()
+}
+
+/// Returns false if `address` is out of range.
+///
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new` and `value` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_memory(machine: *const IntcodeMachine, address: usize, value: *mut i64) -> bool {
+    let machine = &*machine;
+
+    match machine.program.memory().get(address) {
+        Some(word) => {
+            *value = *word;
+            true
+        },
+        None => false
+    }
+}
+
+/// Returns false if `address` is out of range.
+///
+/// # Safety
+///
+/// `machine` must be a live pointer from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_write_memory(machine: *mut IntcodeMachine, address: usize, value: i64) -> bool {
+    let machine = &mut *machine;
+
+    match machine.program.memory_mut().get_mut(address) {
+        Some(word) => {
+            *word = value;
+            true
+        },
+        None => false
+    }
+}
+
+#[test]
+fn test_ffi() {
+    let image = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+    unsafe {
+        let machine = intcode_new(image.as_ptr(), image.len());
+        assert_eq!(intcode_run_until_event(machine), IntcodeStatus::NeedsInput);
+
+        intcode_push_input(machine, 8);
+        assert_eq!(intcode_run_until_event(machine), IntcodeStatus::Output);
+        assert_eq!(intcode_output(machine), 1);
+        assert_eq!(intcode_run_until_event(machine), IntcodeStatus::Complete);
+
+        let mut value = 0;
+        assert!(intcode_write_memory(machine, 10, 42));
+        assert!(intcode_read_memory(machine, 10, &mut value));
+        assert_eq!(value, 42);
+        assert!(!intcode_read_memory(machine, intcode_memory_size(machine), &mut value));
+
+        intcode_free(machine);
+    }
+}
+
+#[test]
+fn test_ffi_error() {
+    let image = [42];
+    let mut buffer = [0 as c_char; 8];
+
+    unsafe {
+        let machine = intcode_new(image.as_ptr(), image.len());
+        assert_eq!(intcode_error_message(machine, buffer.as_mut_ptr(), buffer.len()), 0);
+        assert_eq!(intcode_run_until_event(machine), IntcodeStatus::Error);
+
+        let message = "invalid opcode 42 at 0";
+        assert_eq!(intcode_error_message(machine, buffer.as_mut_ptr(), buffer.len()), message.len());
+        let copied: Vec<u8> = buffer.iter().map(|c| *c as u8).collect();
+        assert_eq!(&copied[..], b"invalid\0");
+
+        intcode_free(machine);
+    }
+}
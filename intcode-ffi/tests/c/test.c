@@ -0,0 +1,40 @@
+#include <stdio.h>
+#include <string.h>
+#include "intcode.h"
+
+#define CHECK(condition) \
+    if (!(condition)) { fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); return 1; }
+
+int main(void) {
+    /* Day 5: outputs 1 if the input equals 8, otherwise 0 */
+    const int64_t image[] = { 3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8 };
+    IntcodeMachine *machine = intcode_new(image, sizeof(image) / sizeof(image[0]));
+    int64_t value = 0;
+
+    CHECK(machine != NULL);
+    CHECK(intcode_run_until_event(machine) == IntcodeStatus_NeedsInput);
+
+    intcode_push_input(machine, 8);
+    CHECK(intcode_run_until_event(machine) == IntcodeStatus_Output);
+    CHECK(intcode_output(machine) == 1);
+    CHECK(intcode_run_until_event(machine) == IntcodeStatus_Complete);
+
+    CHECK(intcode_write_memory(machine, 10, 42));
+    CHECK(intcode_read_memory(machine, 10, &value));
+    CHECK(value == 42);
+    CHECK(!intcode_read_memory(machine, intcode_memory_size(machine), &value));
+
+    intcode_free(machine);
+
+    /* A bad opcode is reported rather than crashing the host */
+    const int64_t broken[] = { 42 };
+    char message[64];
+    machine = intcode_new(broken, 1);
+    CHECK(intcode_run_until_event(machine) == IntcodeStatus_Error);
+    CHECK(intcode_error_message(machine, message, sizeof(message)) == strlen("invalid opcode 42 at 0"));
+    CHECK(strcmp(message, "invalid opcode 42 at 0") == 0);
+    intcode_free(machine);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use intcode::{InstructionCode, Memory, Program, ProgramError, ProgramResult, Word};
+use intcode::image::{ProgramImage, SharedMemory};
+use intcode::observer::Observer;
+
+const HISTORY: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Running,
+    Blocked,
+    Halted,
+    Failed(ProgramError)
+}
+
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Running => "running",
+            State::Blocked => "blocked",
+            State::Halted => "halted",
+            State::Failed(_) => "failed"
+        }
+    }
+}
+
+// The step of the last write to each address. Steps are counted from one, so zero is never.
+#[derive(Debug, Default)]
+pub struct Heat {
+    writes: Vec<u64>,
+    now: u64
+}
+
+impl Heat {
+    pub fn last_write(&self, address: usize) -> Option<u64> {
+        self.writes.get(address).copied().filter(|step| *step > 0)
+    }
+}
+
+impl<M: Memory> Observer<M> for Heat {
+    fn before_instruction(&mut self, program: &Program<M>, _code: InstructionCode) {
+        self.now = program.steps();
+    }
+
+    fn on_write(&mut self, address: usize, _value: Word) {
+        if address >= self.writes.len() { self.writes.resize(address + 1, 0); }
+        self.writes[address] = self.now;
+    }
+}
+
+pub struct Machine {
+    pub name: String,
+    pub program: Program<SharedMemory>,
+    pub inputs: VecDeque<Word>,
+    pub outputs: Vec<Word>,
+    pub history: VecDeque<usize>,
+    pub heat: Heat,
+    pub state: State,
+    initial: Vec<Word>
+}
+
+impl Machine {
+    pub fn new(name: String, image: &ProgramImage, inputs: Vec<Word>) -> Self {
+        Machine {
+            name,
+            program: image.instantiate(),
+            inputs: inputs.iter().copied().collect(),
+            outputs: Vec::new(),
+            history: VecDeque::new(),
+            heat: Heat::default(),
+            state: State::Running,
+            initial: inputs
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.program.reset();
+        self.inputs = self.initial.iter().copied().collect();
+        self.outputs.clear();
+        self.history.clear();
+        self.heat = Heat::default();
+        self.state = State::Running;
+    }
+
+    // Inputs are handed over one at a time so the queue shows what has not been read yet
+    pub fn step(&mut self) -> Option<Word> {
+        if let State::Halted | State::Failed(_) = self.state { return None; }
+
+        if self.program.awaiting_input() {
+            match self.inputs.pop_front() {
+                Some(value) => self.program.append_inputs(&mut vec!(value)),
+                None => {
+                    self.state = State::Blocked;
+                    return None;
+                }
+            }
+        }
+
+        let pc = self.program.pc();
+        self.state = State::Running;
+
+        let result = self.program.try_step_with(&mut self.heat);
+        if result.is_ok() {
+            if self.history.len() == HISTORY { self.history.pop_front(); }
+            self.history.push_back(pc);
+        }
+
+        match result {
+            Ok(Some(ProgramResult::Output(value))) => {
+                self.outputs.push(value);
+                return Some(value);
+            },
+            Ok(Some(ProgramResult::Complete)) => self.state = State::Halted,
+            Ok(None) => (),
+            Err(error) => self.state = State::Failed(error)
+        }
+
+        None
+    }
+}
+
+// Machines are chained, each one's outputs feeding the next one's inputs, and a ring also
+// feeds the last one back into the first
+pub struct Rack {
+    pub machines: Vec<Machine>,
+    pub ring: bool
+}
+
+impl Rack {
+    pub fn step(&mut self, index: usize) {
+        if let Some(value) = self.machines[index].step() {
+            let next = index + 1;
+
+            if next < self.machines.len() {
+                self.machines[next].inputs.push_back(value);
+            } else if self.ring {
+                self.machines[0].inputs.push_back(value);
+            }
+        }
+    }
+
+    pub fn round(&mut self) {
+        for index in 0..self.machines.len() {
+            self.step(index);
+        }
+    }
+
+    // Nothing left to do once every machine has stopped or is waiting on an empty queue
+    pub fn settled(&self) -> bool {
+        self.machines.iter().all(|machine| match machine.state {
+            State::Running => false,
+            State::Blocked => machine.inputs.is_empty(),
+            _ => true
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.machines.iter_mut().for_each(Machine::reset);
+    }
+}
+
+#[test]
+fn test_feedback_ring() {
+    let image: ProgramImage = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5".parse().unwrap();
+    let machines = [9, 8, 7, 6, 5].iter()
+        .enumerate()
+        .map(|(index, phase)| {
+            let inputs = if index == 0 { vec!(*phase, 0) } else { vec!(*phase) };
+            Machine::new(format!("amp {}", index), &image, inputs)
+        })
+        .collect();
+    let mut rack = Rack { machines, ring: true };
+
+    while !rack.settled() { rack.round(); }
+    assert_eq!(rack.machines[4].outputs.last(), Some(&139629729));
+    assert!(rack.machines.iter().all(|machine| machine.state == State::Halted));
+
+    rack.reset();
+    while !rack.settled() { rack.round(); }
+    assert_eq!(rack.machines[4].outputs.last(), Some(&139629729));
+}
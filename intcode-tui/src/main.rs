@@ -0,0 +1,185 @@
+mod machine;
+mod ui;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+use crossterm::{execute, terminal};
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use intcode::Word;
+use intcode::image::ProgramImage;
+use machine::{Machine, Rack};
+use ui::View;
+
+const USAGE: &str = "usage: intcode-tui <program> [--input value]... [--phases a,b,...] [--ring]";
+const FRAME: Duration = Duration::from_millis(50);
+const MAX_SPEED: u32 = 1 << 20;
+
+struct Options {
+    path: String,
+    inputs: Vec<Word>,
+    phases: Vec<Word>,
+    ring: bool
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options { path: String::new(), inputs: vec!(), phases: vec!(), ring: false };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => {
+                let value = args.next().ok_or("--input needs a value")?;
+                options.inputs.push(value.parse().map_err(|_| format!("--input: invalid value {}", value))?);
+            },
+            "--phases" => {
+                let value = args.next().ok_or("--phases needs a value")?;
+                options.phases = value.split(',')
+                    .map(|phase| phase.trim().parse().map_err(|_| format!("--phases: invalid value {}", phase)))
+                    .collect::<Result<_, _>>()?;
+            },
+            "--ring" => options.ring = true,
+            _ if options.path.is_empty() && !arg.starts_with("--") => options.path = arg,
+            _ => return Err(format!("unexpected argument {}", arg))
+        }
+    }
+
+    if options.path.is_empty() { return Err(String::from(USAGE)); }
+    Ok(options)
+}
+
+// With phases there is one machine per phase, each reading its phase first, and the
+// extra inputs go to the first machine. This is the day-7 amplifier layout.
+fn build(options: &Options) -> Result<Rack, String> {
+    let code = fs::read_to_string(&options.path).map_err(|e| format!("{}: {}", options.path, e))?;
+    let image: ProgramImage = code.parse().map_err(|e| format!("{}: {}", options.path, e))?;
+
+    if options.phases.is_empty() {
+        let machine = Machine::new(String::from("main"), &image, options.inputs.clone());
+        return Ok(Rack { machines: vec!(machine), ring: options.ring });
+    }
+
+    let machines = options.phases.iter()
+        .enumerate()
+        .map(|(index, phase)| {
+            let mut inputs = vec!(*phase);
+            if index == 0 { inputs.extend(&options.inputs); }
+            Machine::new(format!("amp {}", index), &image, inputs)
+        })
+        .collect();
+
+    Ok(Rack { machines, ring: options.ring })
+}
+
+struct App {
+    rack: Rack,
+    focus: usize,
+    paused: bool,
+    speed: u32,
+    rates: Vec<f64>,
+    mark: (Instant, Vec<u64>)
+}
+
+impl App {
+    fn new(rack: Rack) -> Self {
+        let count = rack.machines.len();
+        App { rack, focus: 0, paused: true, speed: 1, rates: vec!(0.0; count), mark: (Instant::now(), vec!(0; count)) }
+    }
+
+    fn measure(&mut self) {
+        let elapsed = self.mark.0.elapsed();
+        if elapsed < Duration::from_secs(1) { return; }
+
+        let steps: Vec<u64> = self.rack.machines.iter().map(|m| m.program.steps()).collect();
+        for (rate, (now, before)) in self.rates.iter_mut().zip(steps.iter().zip(&self.mark.1)) {
+            *rate = now.saturating_sub(*before) as f64 / elapsed.as_secs_f64();
+        }
+
+        self.mark = (Instant::now(), steps);
+    }
+
+    // Returns false once the user asks to quit
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let count = self.rack.machines.len();
+
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('c') => self.paused = false,
+            KeyCode::Char('s') => {
+                self.paused = true;
+                self.rack.step(self.focus);
+            },
+            KeyCode::Tab | KeyCode::Right => self.focus = (self.focus + 1) % count,
+            KeyCode::BackTab | KeyCode::Left => self.focus = (self.focus + count - 1) % count,
+            KeyCode::Char('+') | KeyCode::Char('=') => self.speed = (self.speed * 2).min(MAX_SPEED),
+            KeyCode::Char('-') => self.speed = (self.speed / 2).max(1),
+            KeyCode::Char('r') => {
+                self.rack.reset();
+                self.paused = true;
+            },
+            _ => ()
+        }
+
+        true
+    }
+}
+
+// Each frame runs `speed` rounds, redraws, then spends what is left of the frame on keys
+fn event_loop<W: Write>(app: &mut App, out: &mut W) -> io::Result<()> {
+    loop {
+        let start = Instant::now();
+
+        if !app.paused {
+            for _ in 0..app.speed {
+                app.rack.round();
+                if app.rack.settled() {
+                    app.paused = true;
+                    break;
+                }
+            }
+        }
+
+        app.measure();
+
+        let (width, height) = terminal::size()?;
+        let view = View { rack: &app.rack, focus: app.focus, paused: app.paused, speed: app.speed, rates: &app.rates };
+        ui::draw(out, &view, width, height)?;
+
+        while event::poll(FRAME.saturating_sub(start.elapsed()))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && !app.key(key.code, key.modifiers) => return Ok(()),
+                Event::Resize(_, _) => execute!(out, terminal::Clear(terminal::ClearType::All))?,
+                _ => ()
+            }
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let mut app = App::new(build(&options)?);
+    let mut out = io::stdout();
+
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(out, EnterAlternateScreen, Hide))
+        .and_then(|_| event_loop(&mut app, &mut out));
+
+    // Always hand the terminal back, even if drawing failed
+    let _ = execute!(out, Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+
+    result.map_err(|e| e.to_string())
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(run);
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{self, Write};
+use crossterm::queue;
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use intcode::Word;
+use intcode::inspect::disassemble;
+use crate::machine::{Heat, Rack, State};
+
+pub struct View<'a> {
+    pub rack: &'a Rack,
+    pub focus: usize,
+    pub paused: bool,
+    pub speed: u32,
+    pub rates: &'a [f64]
+}
+
+// Pads or cuts the text to exactly `width` columns so each frame overwrites the last
+fn put<W: Write>(out: &mut W, x: u16, y: u16, width: usize, color: Color, text: &str) -> io::Result<()> {
+    let text: String = text.chars().take(width).collect();
+    queue!(out, MoveTo(x, y), SetForegroundColor(color), Print(format!("{:<w$}", text, w = width)))
+}
+
+// The most recent write in each cell, when `len` words are spread over `cells` cells
+pub fn heat_cells(heat: &Heat, len: usize, cells: usize) -> Vec<Option<u64>> {
+    let per_cell = len.div_ceil(cells.max(1));
+
+    (0..cells)
+        .map(|cell| (cell * per_cell..((cell + 1) * per_cell).min(len)).filter_map(|a| heat.last_write(a)).max())
+        .collect()
+}
+
+fn shade(age: Option<u64>) -> (Color, char) {
+    match age {
+        None => (Color::DarkGrey, '·'),
+        Some(age) if age < 100 => (Color::Red, '█'),
+        Some(age) if age < 1_000 => (Color::Yellow, '▓'),
+        Some(age) if age < 10_000 => (Color::Green, '▒'),
+        Some(_) => (Color::Blue, '░')
+    }
+}
+
+fn tail(values: impl ExactSizeIterator<Item = Word>, count: usize) -> String {
+    let skip = values.len().saturating_sub(count);
+    let shown: Vec<String> = values.skip(skip).map(|v| v.to_string()).collect();
+    let more = if skip > 0 { format!("{} more, ", skip) } else { String::new() };
+
+    format!("[{}{}]", more, shown.join(", "))
+}
+
+pub fn draw<W: Write>(out: &mut W, view: &View, width: u16, height: u16) -> io::Result<()> {
+    let (width, height) = (width as usize, height as usize);
+    let machine = &view.rack.machines[view.focus];
+    let program = &machine.program;
+
+    let status = if view.paused { "paused" } else { "running" };
+    let header = format!("intcode-tui  {}  {} rounds/frame   space pause  s step  tab machine  +/- speed  r reset  q quit", status, view.speed);
+    put(out, 0, 0, width, Color::White, &header)?;
+
+    let mut x = 0;
+    for (index, other) in view.rack.machines.iter().enumerate() {
+        let label = format!(" {}: {} ", other.name, other.state.name());
+        let color = if index == view.focus { Color::Cyan } else { Color::DarkGrey };
+        if x + label.len() > width { break; }
+
+        if index == view.focus { queue!(out, SetAttribute(Attribute::Reverse))?; }
+        put(out, x as u16, 1, label.len(), color, &label)?;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+        x += label.len() + 1;
+    }
+    put(out, x.min(width) as u16, 1, width.saturating_sub(x), Color::Reset, "")?;
+
+    let split = width / 2;
+    let right = split as u16 + 2;
+    let right_width = width.saturating_sub(split + 2);
+    let memory = program.memory();
+
+    // Recently executed instructions, then a linear sweep from pc
+    put(out, 0, 2, split, Color::White, "disassembly")?;
+    let mut y = 3;
+    for pc in machine.history.iter().skip(machine.history.len().saturating_sub(height.saturating_sub(3) / 3)) {
+        if *pc == program.pc() { continue; }
+        put(out, 0, y as u16, split, Color::DarkGrey, &format!("   {:>6}  {}", pc, disassemble(memory, *pc).0))?;
+        y += 1;
+    }
+
+    let mut address = program.pc();
+    let mut first = true;
+    while y < height {
+        let (text, len) = if address < memory.len() { disassemble(memory, address) } else { (String::new(), 1) };
+        let (color, arrow) = if first { (Color::Yellow, "=>") } else { (Color::Reset, "  ") };
+        let line = if text.is_empty() { String::new() } else { format!("{} {:>6}  {}", arrow, address, text) };
+
+        put(out, 0, y as u16, split, color, &line)?;
+        address += len;
+        first = false;
+        y += 1;
+    }
+
+    let mut lines = vec!(
+        (Color::White, String::from("registers")),
+        (Color::Reset, format!("pc      {}", program.pc())),
+        (Color::Reset, format!("rb      {}", program.relative_base())),
+        (Color::Reset, format!("steps   {}", program.steps())),
+        (Color::Reset, format!("rate    {:.0} steps/s", view.rates.get(view.focus).copied().unwrap_or(0.0))),
+        (Color::Reset, format!("state   {}", machine.state.name()))
+    );
+    if let State::Failed(error) = machine.state {
+        lines.push((Color::Red, format!("        {}", error)));
+    }
+    lines.push((Color::Reset, String::new()));
+    lines.push((Color::Reset, format!("inputs  {}", tail(machine.inputs.iter().copied(), 8))));
+    lines.push((Color::Reset, format!("outputs {}", tail(machine.outputs.iter().copied(), 8))));
+    lines.push((Color::Reset, String::new()));
+
+    for (index, (color, line)) in lines.iter().enumerate() {
+        put(out, right, 2 + index as u16, right_width, *color, line)?;
+    }
+
+    // Whatever room is left below the registers holds the heat map
+    let top = 2 + lines.len() + 1;
+    let rows = height.saturating_sub(top);
+    if rows == 0 || right_width == 0 { return out.flush(); }
+
+    let cells = heat_cells(&machine.heat, memory.len(), rows * right_width);
+    let per_cell = memory.len().div_ceil(cells.len());
+    put(out, right, (top - 1) as u16, right_width, Color::White, &format!("memory writes, {} words per cell", per_cell))?;
+
+    for row in 0..rows {
+        queue!(out, MoveTo(right, (top + row) as u16))?;
+        for column in 0..right_width {
+            let age = cells[row * right_width + column].map(|step| program.steps().saturating_sub(step));
+            let (color, mark) = shade(age);
+            queue!(out, SetForegroundColor(color), Print(mark))?;
+        }
+    }
+
+    queue!(out, SetForegroundColor(Color::Reset))?;
+    out.flush()
+}
+
+#[test]
+fn test_heat_cells() {
+    use intcode::Program;
+
+    let mut program = Program::from(vec!(1101, 2, 3, 9, 1101, 4, 5, 10, 99, 0, 0, 0));
+    let mut heat = Heat::default();
+    while program.step_with(&mut heat) != Some(intcode::ProgramResult::Complete) {}
+
+    assert_eq!(heat_cells(&heat, 12, 4), vec!(None, None, None, Some(2)));
+    assert_eq!(heat_cells(&heat, 12, 12)[9..], [Some(1), Some(2), None]);
+}
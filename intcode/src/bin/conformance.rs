@@ -0,0 +1,30 @@
+use std::env;
+use std::process;
+use intcode::conformance::run_dir;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: conformance <directory>");
+            process::exit(1);
+        }
+    };
+
+    let outcomes = match run_dir(&path) {
+        Ok(outcomes) => outcomes,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    };
+
+    for outcome in &outcomes {
+        println!("{}", outcome);
+    }
+
+    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+    println!("{} passed, {} failed", outcomes.len() - failed, failed);
+
+    if failed > 0 { process::exit(1); }
+}
@@ -0,0 +1,24 @@
+use std::env;
+use std::process;
+use intcode::fuzz::fuzz;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let number = |index: usize, default: u64| match args.get(index) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            eprintln!("usage: fuzz [seed] [iterations]");
+            process::exit(1);
+        }),
+        None => default
+    };
+
+    let (seed, iterations) = (number(0, 0), number(1, 10_000));
+
+    match fuzz(seed, iterations) {
+        Ok(()) => println!("{} cases from seed {}: all engines agree", iterations, seed),
+        Err((case, mismatch)) => {
+            println!("{}\nminimal case:\n{}", mismatch, case);
+            process::exit(1);
+        }
+    }
+}
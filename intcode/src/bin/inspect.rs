@@ -0,0 +1,165 @@
+use std::env;
+use std::fs::{self, File};
+use std::process;
+use intcode::{Program, ProgramResult, Word};
+use intcode::callstack::CallStack;
+use intcode::inspect::{self, Change};
+use intcode::parse;
+
+const USAGE: &str = "usage: inspect <program> [--set address=value]... [--input value]... [--steps n] [--save path] [--page n] [--rows n]
+       inspect --compare <before> <after> [--page n] [--rows n]";
+
+// Enough for any puzzle program, and stops one that loops forever
+const STEPS: u64 = 10_000_000;
+
+struct Options {
+    path: String,
+    sets: Vec<(usize, Word)>,
+    inputs: Vec<Word>,
+    steps: u64,
+    save: Option<String>,
+    compare: Option<(String, String)>,
+    page: Option<usize>,
+    rows: usize
+}
+
+fn number<T: std::str::FromStr>(value: Option<String>, flag: &str) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    value.parse().map_err(|_| format!("{}: invalid value {}", flag, value))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        path: String::new(),
+        sets: vec!(),
+        inputs: vec!(),
+        steps: STEPS,
+        save: None,
+        compare: None,
+        page: None,
+        rows: 16
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => options.inputs.push(number(args.next(), "--input")?),
+            "--steps" => options.steps = number(args.next(), "--steps")?,
+            "--save" => options.save = Some(args.next().ok_or("--save needs a path")?),
+            "--page" => options.page = Some(number(args.next(), "--page")?),
+            "--rows" => options.rows = number(args.next(), "--rows")?,
+            "--set" => {
+                let value = args.next().ok_or("--set needs a value")?;
+                let (address, word) = value.split_once('=').ok_or(format!("--set: expected address=value, got {}", value))?;
+                options.sets.push((number(Some(address.to_string()), "--set")?, number(Some(word.to_string()), "--set")?));
+            },
+            "--compare" => {
+                let before = args.next().ok_or("--compare needs two snapshots")?;
+                let after = args.next().ok_or("--compare needs two snapshots")?;
+                options.compare = Some((before, after));
+            },
+            _ if options.path.is_empty() && !arg.starts_with("--") => options.path = arg,
+            _ => return Err(format!("unexpected argument {}", arg))
+        }
+    }
+
+    match (&options.compare, options.path.is_empty()) {
+        (Some(_), false) => Err(String::from("--compare takes snapshots in place of a program")),
+        (None, true) => Err(String::from(USAGE)),
+        _ => Ok(options)
+    }
+}
+
+// Snapshots are memory saved with --save, in the same format as a program
+fn load(path: &str) -> Result<Vec<Word>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse::parse_reader(file).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn show(memory: &[Word], changes: &[Change], options: &Options) -> Result<(), String> {
+    println!("{} words changed in {} ranges", changes.len(), inspect::ranges(changes).len());
+    print!("{}", inspect::report(changes));
+
+    // Without a page, show every page holding a change
+    let rows = options.rows.max(1);
+    let pages: Vec<usize> = match options.page {
+        Some(page) => vec!(page),
+        None => {
+            let size = rows.checked_mul(inspect::COLUMNS).ok_or(format!("--rows: {} is too many", rows))?;
+            let mut pages: Vec<usize> = changes.iter().map(|c| c.address / size).collect();
+            pages.dedup();
+            pages
+        }
+    };
+
+    for page in pages {
+        let dump = inspect::page(memory, changes, page, rows).map_err(|e| format!("--page: {}", e))?;
+        println!("\npage {}", page);
+        print!("{}", dump);
+    }
+
+    Ok(())
+}
+
+fn compare(before: &str, after: &str, options: &Options) -> Result<(), String> {
+    let (before, after) = (load(before)?, load(after)?);
+    show(&after, &inspect::diff(&before, &after), options)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let file = File::open(&options.path).map_err(|e| format!("{}: {}", options.path, e))?;
+    let mut program = Program::from_reader(file).map_err(|e| format!("{}: {}", options.path, e))?;
+
+    for (address, value) in &options.sets {
+        let word = program.memory_mut().get_mut(*address).ok_or(format!("--set: address {} is out of range", address))?;
+        *word = *value;
+    }
+
+    let before = program.memory().to_vec();
+    let mut stack = CallStack::new();
+    let mut outputs = Vec::new();
+    program.append_inputs(&mut options.inputs.clone());
+
+    let error = loop {
+        if program.steps() >= options.steps {
+            break Some(format!("stopped after {} steps", options.steps));
+        }
+
+        match program.try_step_with(&mut stack) {
+            Ok(Some(ProgramResult::Output(value))) => outputs.push(value),
+            Ok(Some(ProgramResult::Complete)) => break None,
+            Ok(None) => (),
+            Err(error) => break Some(error.to_string())
+        }
+    };
+
+    if let Some(path) = &options.save {
+        let words: Vec<String> = program.memory().iter().map(|word| word.to_string()).collect();
+        fs::write(path, words.join(",") + "\n").map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    let changes = inspect::diff(&before, program.memory());
+
+    println!("outputs: {:?}", outputs);
+    if let Some(error) = error {
+        println!("error: {}", error);
+        print!("{}", stack.backtrace(program.pc()));
+    }
+    if !stack.calls().is_empty() {
+        let calls: Vec<String> = stack.calls().iter().map(|(entry, count)| format!("func_{} x{}", entry, count)).collect();
+        println!("calls: {}", calls.join(", "));
+    }
+
+    show(program.memory(), &changes, options)
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|options| match &options.compare {
+        Some((before, after)) => compare(before, after, &options),
+        None => run(&options)
+    });
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
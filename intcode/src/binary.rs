@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use crate::Word;
+
+// Layout, all integers after the fixed header are LEB128 varints:
+//
+//   "ICOD" version:u8 width:u8 flags:u8
+//   count  word*            (words are zigzag encoded)
+//   count  (address name)*  (if flags & SYMBOLS)
+//   count  (key value)*     (if flags & METADATA)
+//
+// Strings are a length followed by UTF-8 bytes. `width` is the smallest of 8, 16, 32 or 64 bits
+// that holds every word, so narrower VMs can reject an image up front.
+const MAGIC: &[u8; 4] = b"ICOD";
+const VERSION: u8 = 1;
+const SYMBOLS: u8 = 1;
+const METADATA: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum BinaryError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnsupportedWidth(u8),
+    WordOutOfRange { index: usize, value: Word },
+    Truncated,
+    InvalidString,
+    Io(String)
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::BadMagic => write!(f, "not an intcode image"),
+            BinaryError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            BinaryError::UnsupportedWidth(w) => write!(f, "unsupported word width {}", w),
+            BinaryError::WordOutOfRange { index, value } => write!(f, "word {} ({}) does not fit the declared width", index, value),
+            BinaryError::Truncated => write!(f, "image is truncated"),
+            BinaryError::InvalidString => write!(f, "string is not valid UTF-8"),
+            BinaryError::Io(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl From<io::Error> for BinaryError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => BinaryError::Truncated,
+            _ => BinaryError::Io(e.to_string())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Container {
+    pub words: Vec<Word>,
+    pub symbols: BTreeMap<usize, String>,
+    pub metadata: BTreeMap<String, String>
+}
+
+fn width_of(words: &[Word]) -> u8 {
+    [8, 16, 32].iter()
+        .find(|&&bits| words.iter().all(|w| fits(*w, bits)))
+        .copied()
+        .unwrap_or(64)
+}
+
+fn fits(word: Word, bits: u8) -> bool {
+    bits >= 64 || (word >= -(1 << (bits - 1)) && word < (1 << (bits - 1)))
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, BinaryError> {
+    let mut value: u64 = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(BinaryError::Truncated)
+}
+
+fn write_string<W: Write>(writer: &mut W, string: &str) -> io::Result<()> {
+    write_varint(writer, string.len() as u64)?;
+    writer.write_all(string.as_bytes())
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, BinaryError> {
+    let mut bytes = Vec::new();
+    let length = read_varint(reader)?;
+    reader.take(length).read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 != length { return Err(BinaryError::Truncated); }
+    String::from_utf8(bytes).map_err(|_| BinaryError::InvalidString)
+}
+
+fn zigzag(word: Word) -> u64 {
+    ((word << 1) ^ (word >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> Word {
+    ((value >> 1) as Word) ^ -((value & 1) as Word)
+}
+
+impl Container {
+    pub fn new(words: Vec<Word>) -> Self {
+        Container { words, symbols: BTreeMap::new(), metadata: BTreeMap::new() }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut flags = 0;
+        if !self.symbols.is_empty() { flags |= SYMBOLS; }
+        if !self.metadata.is_empty() { flags |= METADATA; }
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, width_of(&self.words), flags])?;
+
+        write_varint(&mut writer, self.words.len() as u64)?;
+        for word in &self.words {
+            write_varint(&mut writer, zigzag(*word))?;
+        }
+
+        if flags & SYMBOLS != 0 {
+            write_varint(&mut writer, self.symbols.len() as u64)?;
+            for (address, name) in &self.symbols {
+                write_varint(&mut writer, *address as u64)?;
+                write_string(&mut writer, name)?;
+            }
+        }
+
+        if flags & METADATA != 0 {
+            write_varint(&mut writer, self.metadata.len() as u64)?;
+            for (key, value) in &self.metadata {
+                write_string(&mut writer, key)?;
+                write_string(&mut writer, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BinaryError> {
+        let mut header = [0u8; 7];
+        reader.read_exact(&mut header)?;
+
+        if &header[..4] != MAGIC { return Err(BinaryError::BadMagic); }
+        if header[4] != VERSION { return Err(BinaryError::UnsupportedVersion(header[4])); }
+
+        let (width, flags) = (header[5], header[6]);
+        if ![8, 16, 32, 64].contains(&width) { return Err(BinaryError::UnsupportedWidth(width)); }
+
+        let mut container = Container::default();
+        let count = read_varint(&mut reader)?;
+
+        for index in 0..count as usize {
+            let value = unzigzag(read_varint(&mut reader)?);
+            if !fits(value, width) { return Err(BinaryError::WordOutOfRange { index, value }); }
+            container.words.push(value);
+        }
+
+        if flags & SYMBOLS != 0 {
+            for _ in 0..read_varint(&mut reader)? {
+                let address = read_varint(&mut reader)? as usize;
+                container.symbols.insert(address, read_string(&mut reader)?);
+            }
+        }
+
+        if flags & METADATA != 0 {
+            for _ in 0..read_varint(&mut reader)? {
+                let key = read_string(&mut reader)?;
+                container.metadata.insert(key, read_string(&mut reader)?);
+            }
+        }
+
+        Ok(container)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryError> {
+        Container::read(bytes)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let mut container = Container::new(vec!(109, 1, 204, -1, 1125899906842624, 99));
+    container.symbols.insert(0, String::from("main"));
+    container.metadata.insert(String::from("source"), String::from("day-9"));
+
+    let bytes = container.to_bytes();
+    assert_eq!(&bytes[..7], b"ICOD\x01\x40\x03");
+    assert_eq!(Container::from_bytes(&bytes), Ok(container));
+}
+
+#[test]
+fn test_read_errors() {
+    let bytes = Container::new(vec!(1, 2, 3, 99)).to_bytes();
+    assert_eq!(bytes.len(), 13);
+
+    assert_eq!(Container::from_bytes(b"CODE\x01\x08\x00\x00"), Err(BinaryError::BadMagic));
+    assert_eq!(Container::from_bytes(&bytes[..10]), Err(BinaryError::Truncated));
+    assert_eq!(Container::from_bytes(b"ICOD\x01\x08\x00\x01\x80\x04"), Err(BinaryError::WordOutOfRange { index: 0, value: 256 }));
+}
@@ -0,0 +1,158 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{InstructionCode, Memory, Program, Word};
+use crate::observer::Observer;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub entry: usize,
+    pub call_site: Option<usize>,
+    pub base: Word,
+    pub arguments: Vec<Word>
+}
+
+impl Frame {
+    fn return_address(&self) -> Option<usize> {
+        self.call_site.map(|pc| pc + InstructionCode::JumpIfTrue.length())
+    }
+}
+
+// A call is a taken jump whose fall-through address was stored since the last taken jump,
+// and the words stored right after that slot are its arguments. A return is a taken jump to
+// the return address of a live frame.
+#[derive(Debug)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    calls: BTreeMap<usize, u64>,
+    writes: Vec<(usize, Word)>,
+    base: Word
+}
+
+impl Default for CallStack {
+    fn default() -> Self {
+        CallStack::new()
+    }
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        CallStack {
+            frames: vec!(Frame { entry: 0, call_site: None, base: 0, arguments: Vec::new() }),
+            calls: BTreeMap::new(),
+            writes: Vec::new(),
+            base: 0
+        }
+    }
+
+    // Outermost first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn calls(&self) -> &BTreeMap<usize, u64> {
+        &self.calls
+    }
+
+    // Innermost first. Each frame is shown at the pc it is currently at, which for callers
+    // is the call they are waiting on.
+    pub fn backtrace(&self, pc: usize) -> String {
+        let mut output = String::new();
+        let mut at = pc;
+
+        for (depth, frame) in self.frames.iter().rev().enumerate() {
+            let name = match frame.call_site {
+                Some(_) => format!("func_{}", frame.entry),
+                None => String::from("entry")
+            };
+
+            output.push_str(&format!("#{} {} at {}, base {}", depth, name, at, frame.base));
+            if !frame.arguments.is_empty() {
+                output.push_str(&format!(", args {:?}", frame.arguments));
+            }
+            output.push('\n');
+
+            at = frame.call_site.unwrap_or(at);
+        }
+
+        output
+    }
+
+    fn call(&mut self, pc: usize, target: usize) -> bool {
+        let return_address = (pc + InstructionCode::JumpIfTrue.length()) as Word;
+        let slot = match self.writes.iter().rev().find(|(_, value)| *value == return_address) {
+            Some((slot, _)) => *slot,
+            None => return false
+        };
+
+        let written = |address: usize| self.writes.iter().rev().find(|(a, _)| *a == address).map(|(_, v)| *v);
+        let arguments = (slot + 1..).map_while(written).collect();
+
+        self.frames.push(Frame { entry: target, call_site: Some(pc), base: self.base, arguments });
+        *self.calls.entry(target).or_insert(0) += 1;
+
+        true
+    }
+
+    // Returning past several frames at once unwinds all of them
+    fn ret(&mut self, target: usize) -> bool {
+        match self.frames.iter().rposition(|frame| frame.return_address() == Some(target)) {
+            Some(depth) => {
+                self.frames.truncate(depth);
+                true
+            },
+            None => false
+        }
+    }
+}
+
+impl<M: Memory> Observer<M> for CallStack {
+    fn after_instruction(&mut self, program: &Program<M>, pc: usize, code: InstructionCode) {
+        if let InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse = code {
+            let target = program.pc();
+            if target == pc + code.length() { return; }
+
+            if !self.ret(target) {
+                self.call(pc, target);
+            }
+
+            self.writes.clear();
+        }
+    }
+
+    fn on_write(&mut self, address: usize, value: Word) {
+        self.writes.push((address, value));
+    }
+
+    fn on_relative_base(&mut self, _old: Word, new: Word) {
+        self.base = new;
+        if let Some(frame) = self.frames.last_mut() {
+            frame.base = new;
+        }
+    }
+}
+
+#[test]
+fn test_backtrace() {
+    use crate::ProgramResult;
+
+    // main passes 5 to f, which passes double that to g, which outputs it
+    let mut program = Program::from(String::from("\
+        109,100, 21101,5,0,1, 21101,13,0,0, 1105,1,18, 4,101, 99, 0,0,
+        109,2, 21202,-1,2,1, 21101,31,0,0, 1105,1,40, 109,-2, 2105,1,0, 0,0,0,0,
+        109,2, 204,-1, 109,-2, 2105,1,0"));
+    let mut stack = CallStack::new();
+
+    assert_eq!(program.run_until_event_with(&mut stack), ProgramResult::Output(10));
+    assert_eq!(stack.backtrace(program.pc()), "\
+#0 func_40 at 44, base 104, args [10]
+#1 func_18 at 28, base 102, args [5]
+#2 entry at 10, base 100
+");
+
+    assert_eq!(program.run_until_event_with(&mut stack), ProgramResult::Output(5));
+    assert_eq!(stack.frames().len(), 1);
+    assert_eq!(stack.calls().iter().collect::<Vec<_>>(), vec!((&18, &1), (&40, &1)));
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use crate::{Program, ProgramError, ProgramResult, Word};
+
+const DEFAULT_STEPS: u64 = 1_000_000;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ErrorKind {
+    Parse,
+    NoInput,
+    InvalidOpcode,
+    AddressOutOfRange,
+    Overflow,
+    StepLimit
+}
+
+// Vectors run in growable memory, so an image that does not fit never comes up
+impl From<ProgramError> for ErrorKind {
+    fn from(error: ProgramError) -> Self {
+        match error {
+            ProgramError::NoInput { .. } => ErrorKind::NoInput,
+            ProgramError::InvalidOpcode { .. } => ErrorKind::InvalidOpcode,
+            ProgramError::AddressOutOfRange { .. } | ProgramError::ImageTooLarge { .. } => ErrorKind::AddressOutOfRange,
+            ProgramError::Overflow { .. } => ErrorKind::Overflow
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Parse => write!(f, "parse"),
+            ErrorKind::NoInput => write!(f, "no-input"),
+            ErrorKind::InvalidOpcode => write!(f, "invalid-opcode"),
+            ErrorKind::AddressOutOfRange => write!(f, "address"),
+            ErrorKind::Overflow => write!(f, "overflow"),
+            ErrorKind::StepLimit => write!(f, "step-limit")
+        }
+    }
+}
+
+impl FromStr for ErrorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "parse" => Ok(ErrorKind::Parse),
+            "no-input" => Ok(ErrorKind::NoInput),
+            "invalid-opcode" => Ok(ErrorKind::InvalidOpcode),
+            "address" => Ok(ErrorKind::AddressOutOfRange),
+            "overflow" => Ok(ErrorKind::Overflow),
+            "step-limit" => Ok(ErrorKind::StepLimit),
+            other => Err(format!("unknown error '{}'", other))
+        }
+    }
+}
+
+// Outputs are only checked when the vector lists them, so memory-only vectors stay short
+#[derive(Debug, PartialEq)]
+pub struct Vector {
+    pub name: String,
+    pub program: String,
+    pub inputs: Vec<Word>,
+    pub outputs: Option<Vec<Word>>,
+    pub memory: Vec<(usize, Word)>,
+    pub error: Option<ErrorKind>,
+    pub steps: u64
+}
+
+impl Vector {
+    pub fn new(name: &str, program: &str) -> Self {
+        Vector {
+            name: String::from(name),
+            program: String::from(program),
+            inputs: Vec::new(),
+            outputs: None,
+            memory: Vec::new(),
+            error: None,
+            steps: DEFAULT_STEPS
+        }
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let mut program: Program = match self.program.parse() {
+            Ok(program) => program,
+            Err(_) if self.error == Some(ErrorKind::Parse) => return Ok(()),
+            Err(e) => return Err(format!("program does not parse: {}", e))
+        };
+
+        let mut inputs = self.inputs.iter().copied();
+        let mut outputs = Vec::new();
+
+        // Inputs are fed one at a time so running out shows up as a no-input error
+        let error = loop {
+            if program.steps() >= self.steps { break Some(ErrorKind::StepLimit); }
+
+            if program.awaiting_input() {
+                if let Some(value) = inputs.next() { program.append_inputs(&mut vec!(value)); }
+            }
+
+            match program.try_step() {
+                Ok(Some(ProgramResult::Output(value))) => outputs.push(value),
+                Ok(Some(ProgramResult::Complete)) => break None,
+                Ok(None) => (),
+                Err(error) => break Some(ErrorKind::from(error))
+            }
+        };
+
+        if error != self.error {
+            let describe = |e: Option<ErrorKind>| e.map_or(String::from("completion"), |e| e.to_string());
+            return Err(format!("expected {}, found {} at pc {} after {} steps",
+                describe(self.error), describe(error), program.pc(), program.steps()));
+        }
+
+        if let Some(expected) = &self.outputs {
+            if *expected != outputs {
+                return Err(format!("expected outputs {:?}, found {:?}", expected, outputs));
+            }
+        }
+
+        for (address, expected) in &self.memory {
+            match program.memory().get(*address) {
+                Some(value) if value == expected => (),
+                Some(value) => return Err(format!("expected memory[{}] = {}, found {}", address, expected, value)),
+                None => return Err(format!("memory[{}] is out of range", address))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn values(text: &str) -> Result<Vec<Word>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(|_| format!("invalid number '{}'", item)))
+        .collect()
+}
+
+fn cells(text: &str) -> Result<Vec<(usize, Word)>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (address, value) = item.split_once('=').ok_or(format!("expected address=value, found '{}'", item))?;
+            let address = address.parse().map_err(|_| format!("invalid address '{}'", address))?;
+            let value = value.parse().map_err(|_| format!("invalid number '{}'", value))?;
+            Ok((address, value))
+        })
+        .collect()
+}
+
+// Each vector starts with a `[name]` header followed by `key: value` lines:
+// program, input, output, memory (address=value pairs), error and steps. Errors are
+// parse, no-input, invalid-opcode, address, overflow or step-limit.
+pub fn parse(contents: &str) -> Result<Vec<Vector>, String> {
+    let mut vectors: Vec<Vector> = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            vectors.push(Vector::new(name.trim(), ""));
+            continue;
+        }
+
+        let at_line = |message: String| format!("line {}: {}", number + 1, message);
+        let vector = vectors.last_mut().ok_or_else(|| at_line(String::from("expected a [name] header")))?;
+        let (key, value) = line.split_once(':').ok_or_else(|| at_line(String::from("expected 'key: value'")))?;
+        let value = value.trim();
+
+        match key.trim() {
+            "program" => vector.program = String::from(value),
+            "input" => vector.inputs = values(value).map_err(at_line)?,
+            "output" => vector.outputs = Some(values(value).map_err(at_line)?),
+            "memory" => vector.memory = cells(value).map_err(at_line)?,
+            "error" => vector.error = Some(value.parse().map_err(at_line)?),
+            "steps" => vector.steps = value.parse().map_err(|_| at_line(format!("invalid step limit '{}'", value)))?,
+            other => return Err(at_line(format!("unknown key '{}'", other)))
+        }
+    }
+
+    match vectors.iter().find(|v| v.program.is_empty()) {
+        Some(vector) => Err(format!("{}: missing program", vector.name)),
+        None => Ok(vectors)
+    }
+}
+
+pub fn load(path: &str) -> Result<Vec<Vector>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse(&contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[derive(Debug)]
+pub struct Outcome {
+    pub file: String,
+    pub name: String,
+    pub result: Result<(), String>
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(()) => write!(f, "ok   {} [{}]", self.file, self.name),
+            Err(message) => write!(f, "FAIL {} [{}]: {}", self.file, self.name, message)
+        }
+    }
+}
+
+// Runs every `.txt` file in the directory, in name order
+pub fn run_dir(path: &str) -> Result<Vec<Outcome>, String> {
+    let mut files: Vec<_> = fs::read_dir(path).map_err(|e| format!("{}: {}", path, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|file| file.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    files.sort();
+
+    let mut outcomes = Vec::new();
+
+    for file in files {
+        let name = file.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+
+        for vector in load(&file.to_string_lossy())? {
+            outcomes.push(Outcome { file: name.clone(), name: vector.name.clone(), result: vector.run() });
+        }
+    }
+
+    Ok(outcomes)
+}
+
+#[test]
+fn test_parse_vectors() {
+    let vectors = parse("# comment\n[add]\nprogram: 1,0,0,0,99\nmemory: 0=2\n\n[echo]\nprogram: 3,0,4,0,99\ninput: 7\noutput: 7\n").unwrap();
+
+    assert_eq!(vectors.len(), 2);
+    assert_eq!(vectors[0].memory, vec!((0, 2)));
+    assert_eq!(vectors[1].inputs, vec!(7));
+    assert_eq!(vectors[1].outputs, Some(vec!(7)));
+    assert!(vectors.iter().all(|v| v.run().is_ok()));
+
+    assert_eq!(parse("program: 99"), Err(String::from("line 1: expected a [name] header")));
+    assert_eq!(parse("[x]\nprogram: 99\nerror: boom"), Err(String::from("line 3: unknown error 'boom'")));
+}
+
+#[test]
+fn test_vector_errors() {
+    let mut vector = Vector::new("needs input", "3,0,99");
+    vector.error = Some(ErrorKind::NoInput);
+    assert_eq!(vector.run(), Ok(()));
+
+    let mut vector = Vector::new("spins", "1105,1,0");
+    vector.steps = 100;
+    assert_eq!(vector.run(), Err(String::from("expected completion, found step-limit at pc 0 after 100 steps")));
+
+    let mut vector = Vector::new("wrong output", "104,1,99");
+    vector.outputs = Some(vec!(2));
+    assert_eq!(vector.run(), Err(String::from("expected outputs [2], found [1]")));
+}
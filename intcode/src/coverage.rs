@@ -0,0 +1,208 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use crate::{InstructionCode, Memory, Program, Word};
+use crate::lint::walk;
+use crate::observer::Observer;
+
+const EXECUTED: u8 = 1;
+const READ: u8 = 2;
+const WRITTEN: u8 = 4;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Branch {
+    pub taken: bool,
+    pub not_taken: bool
+}
+
+#[derive(Debug, Default)]
+pub struct Coverage {
+    flags: Vec<u8>,
+    branches: BTreeMap<usize, Branch>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub instructions: usize,
+    pub executed: usize,
+    pub branches: usize,
+    pub branches_taken: usize,
+    pub read: usize,
+    pub written: usize
+}
+
+impl Summary {
+    pub fn percentage(&self) -> f64 {
+        percent(self.executed, self.instructions)
+    }
+
+    pub fn branch_percentage(&self) -> f64 {
+        percent(self.branches_taken, self.branches)
+    }
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 { 100.0 } else { part as f64 * 100.0 / total as f64 }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instructions {}/{} ({:.1}%), branches {}/{} ({:.1}%), data read {}, written {}",
+            self.executed, self.instructions, self.percentage(),
+            self.branches_taken, self.branches, self.branch_percentage(),
+            self.read, self.written)
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage { flags: Vec::new(), branches: BTreeMap::new() }
+    }
+
+    fn mark(&mut self, address: usize, flag: u8) {
+        if address >= self.flags.len() {
+            self.flags.resize(address + 1, 0);
+        }
+
+        self.flags[address] |= flag;
+    }
+
+    pub fn executed(&self, address: usize) -> bool {
+        self.has(address, EXECUTED)
+    }
+
+    pub fn was_read(&self, address: usize) -> bool {
+        self.has(address, READ)
+    }
+
+    pub fn was_written(&self, address: usize) -> bool {
+        self.has(address, WRITTEN)
+    }
+
+    pub fn branch_at(&self, address: usize) -> Branch {
+        self.branches.get(&address).copied().unwrap_or_default()
+    }
+
+    fn has(&self, address: usize, flag: u8) -> bool {
+        self.flags.get(address).is_some_and(|f| f & flag != 0)
+    }
+
+    // Splits the image into instructions and data words. Instructions are everything reachable
+    // from the entry point or an executed address.
+    fn units(&self, image: &[Word]) -> Vec<(usize, Option<InstructionCode>)> {
+        let mut roots: Vec<usize> = (0..image.len()).filter(|a| self.executed(*a)).collect();
+        roots.push(0);
+
+        let starts = walk(image, roots);
+        let mut units = Vec::new();
+        let mut address = 0;
+
+        while address < image.len() {
+            let code = starts.get(&address).copied().flatten();
+            units.push((address, code));
+            address += code.map_or(1, |c| c.length());
+        }
+
+        units
+    }
+
+    pub fn summary(&self, image: &[Word]) -> Summary {
+        let units = self.units(image);
+        let instructions: Vec<&(usize, Option<InstructionCode>)> = units.iter().filter(|(_, code)| code.is_some()).collect();
+        let branches: Vec<Branch> = instructions.iter()
+            .filter(|(_, code)| matches!(code, Some(InstructionCode::JumpIfTrue) | Some(InstructionCode::JumpIfFalse)))
+            .map(|(address, _)| self.branch_at(*address))
+            .collect();
+
+        Summary {
+            instructions: instructions.len(),
+            executed: instructions.iter().filter(|(address, _)| self.executed(*address)).count(),
+            branches: branches.len() * 2,
+            branches_taken: branches.iter().map(|b| b.taken as usize + b.not_taken as usize).sum(),
+            read: (0..self.flags.len()).filter(|a| self.was_read(*a)).count(),
+            written: (0..self.flags.len()).filter(|a| self.was_written(*a)).count()
+        }
+    }
+
+    pub fn listing(&self, image: &[Word]) -> String {
+        let mut listing = String::new();
+
+        for (address, code) in self.units(image) {
+            let length = code.map_or(1, |c| c.length()).min(image.len() - address);
+            let words: Vec<String> = image[address..address + length].iter().map(|w| w.to_string()).collect();
+
+            let marker = match code {
+                Some(_) if self.executed(address) => 'X',
+                Some(_) => '-',
+                None => ' '
+            };
+
+            let read = if self.was_read(address) { 'R' } else { ' ' };
+            let written = if self.was_written(address) { 'W' } else { ' ' };
+            let mut line = format!("{:>6} {}{}{} {:<24}", address, marker, read, written, words.join(","));
+
+            if let Some(code) = code {
+                line.push_str(&format!(" {:?}", code));
+            }
+
+            if let Some(InstructionCode::JumpIfTrue) | Some(InstructionCode::JumpIfFalse) = code {
+                let branch = self.branch_at(address);
+                line.push_str(&format!(" [{}{}]",
+                    if branch.taken { 'T' } else { '-' },
+                    if branch.not_taken { 'F' } else { '-' }));
+            }
+
+            listing.push_str(line.trim_end());
+            listing.push('\n');
+        }
+
+        listing
+    }
+}
+
+impl<M: Memory> Observer<M> for Coverage {
+    fn before_instruction(&mut self, program: &Program<M>, _code: InstructionCode) {
+        self.mark(program.pc(), EXECUTED);
+    }
+
+    fn on_branch(&mut self, pc: usize, taken: bool) {
+        let branch = self.branches.entry(pc).or_default();
+        if taken { branch.taken = true; } else { branch.not_taken = true; }
+    }
+
+    fn on_read(&mut self, address: usize, _value: Word) {
+        self.mark(address, READ);
+    }
+
+    fn on_write(&mut self, address: usize, _value: Word) {
+        self.mark(address, WRITTEN);
+    }
+}
+
+#[test]
+fn test_coverage() {
+    let mut program = Program::from(String::from("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9"));
+    let mut coverage = Coverage::new();
+    assert_eq!(program.run_with(&mut vec!(0), &mut coverage), vec!(0));
+
+    let summary = coverage.summary(program.image());
+    assert_eq!(summary.instructions, 5);
+    assert_eq!(summary.executed, 4);
+    assert_eq!(summary.branches, 2);
+    assert_eq!(summary.branches_taken, 1);
+    assert!(coverage.was_written(12));
+    assert!(coverage.listing(program.image()).contains("     5 -   1,13,14,13               Addition"));
+}
+
+#[test]
+fn test_coverage_branch_to_next_instruction() {
+    // Both jumps land on the following instruction whichever way they go
+    let mut program = Program::from(String::from("1105,1,3,1106,1,6,99"));
+    let mut coverage = Coverage::new();
+    program.run_with(&mut vec!(), &mut coverage);
+
+    assert_eq!(coverage.branch_at(0), Branch { taken: true, not_taken: false });
+    assert_eq!(coverage.branch_at(3), Branch { taken: false, not_taken: true });
+}
@@ -0,0 +1,389 @@
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{InstructionCode, ParameterMode, Word};
+use crate::lint::walk;
+
+struct Op {
+    address: usize,
+    code: InstructionCode,
+    params: Vec<(ParameterMode, Word)>
+}
+
+impl Op {
+    fn decode(image: &[Word], address: usize, code: InstructionCode) -> Self {
+        let params = (1..code.length()).map(|n| {
+            let mode = match (image[address] / Word::pow(10, n as u32 + 1)) % 10 {
+                1 => ParameterMode::Immediate,
+                2 => ParameterMode::Relative,
+                _ => ParameterMode::Position
+            };
+
+            (mode, image.get(address + n).copied().unwrap_or(0))
+        }).collect();
+
+        Op { address, code, params }
+    }
+
+    fn end(&self) -> usize {
+        self.address + self.code.length()
+    }
+
+    fn is_jump(&self) -> bool {
+        matches!(self.code, InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse)
+    }
+
+    fn constant_condition(&self) -> Option<bool> {
+        match self.params.first() {
+            Some((ParameterMode::Immediate, value)) if self.is_jump() => Some(*value != 0),
+            _ => None
+        }
+    }
+
+    fn unconditional(&self) -> bool {
+        matches!((&self.code, self.constant_condition()),
+            (InstructionCode::JumpIfTrue, Some(true)) | (InstructionCode::JumpIfFalse, Some(false)))
+    }
+
+    fn conditional(&self) -> bool {
+        self.is_jump() && self.constant_condition().is_none()
+    }
+
+    fn target(&self) -> Option<usize> {
+        match self.params.get(1) {
+            Some((ParameterMode::Immediate, value)) if self.is_jump() && *value >= 0 => Some(*value as usize),
+            _ => None
+        }
+    }
+}
+
+fn operand((mode, value): (ParameterMode, Word)) -> String {
+    match mode {
+        ParameterMode::Immediate => value.to_string(),
+        ParameterMode::Position => format!("mem[{}]", value),
+        ParameterMode::Relative if value >= 0 => format!("local{}", value),
+        ParameterMode::Relative => format!("arg{}", value.unsigned_abs())
+    }
+}
+
+// Writes never use immediate mode, the VM treats it as a position
+fn destination((mode, value): (ParameterMode, Word)) -> String {
+    match mode {
+        ParameterMode::Immediate => operand((ParameterMode::Position, value)),
+        _ => operand((mode, value))
+    }
+}
+
+struct Line {
+    address: Option<usize>,
+    depth: usize,
+    text: String
+}
+
+struct Decompiler {
+    ops: Vec<Op>,
+    calls: BTreeSet<usize>,
+    functions: BTreeSet<usize>,
+    labels: BTreeSet<usize>,
+    lines: Vec<Line>
+}
+
+impl Decompiler {
+    fn new(image: &[Word]) -> Self {
+        let mut roots = vec!(0);
+        let mut decompiler = Decompiler {
+            ops: Vec::new(),
+            calls: BTreeSet::new(),
+            functions: BTreeSet::new(),
+            labels: BTreeSet::new(),
+            lines: Vec::new()
+        };
+
+        // Call sites are plain jumps, so the code after them is only reachable through the
+        // callee's return. Keep walking until no new return points turn up.
+        loop {
+            decompiler.ops = walk(image, roots.clone()).into_iter()
+                .filter_map(|(address, code)| code.map(|c| Op::decode(image, address, c)))
+                .collect();
+
+            decompiler.find_calls();
+
+            let returns: Vec<usize> = decompiler.calls.iter().map(|i| decompiler.ops[*i].end()).collect();
+            let before = roots.len();
+            for address in returns {
+                if !roots.contains(&address) { roots.push(address); }
+            }
+
+            if roots.len() == before { break; }
+        }
+
+        decompiler
+    }
+
+    // A call stores the address following a jump, then takes the jump
+    fn find_calls(&mut self) {
+        self.calls.clear();
+        self.functions.clear();
+
+        for i in 1..self.ops.len() {
+            let (store, jump) = (&self.ops[i - 1], &self.ops[i]);
+            let stored = match (store.code, store.params.first(), store.params.get(1)) {
+                (InstructionCode::Addition, Some((ParameterMode::Immediate, a)), Some((ParameterMode::Immediate, b))) => a.checked_add(*b),
+                (InstructionCode::Multiplication, Some((ParameterMode::Immediate, a)), Some((ParameterMode::Immediate, b))) => a.checked_mul(*b),
+                _ => None
+            };
+            let stores_return = stored == Some(jump.end() as Word);
+
+            if let (true, true, Some(target)) = (stores_return, jump.unconditional(), jump.target()) {
+                self.calls.insert(i);
+                self.functions.insert(target);
+            }
+        }
+    }
+
+    fn index_of(&self, address: usize) -> Option<usize> {
+        self.ops.binary_search_by_key(&address, |op| op.address).ok()
+    }
+
+    fn push(&mut self, address: Option<usize>, depth: usize, text: String) {
+        self.lines.push(Line { address, depth, text });
+    }
+
+    fn jump_target(&self, op: &Op) -> String {
+        match (op.target(), op.params[1]) {
+            (Some(target), _) if self.functions.contains(&target) => format!("func_{}", target),
+            (Some(target), _) => format!("label_{}", target),
+            (None, param) => format!("*{}", operand(param))
+        }
+    }
+
+    // The condition under which the jump is taken
+    fn taken(&self, op: &Op) -> String {
+        let value = operand(op.params[0]);
+        match op.code {
+            InstructionCode::JumpIfTrue => format!("{} != 0", value),
+            _ => format!("{} == 0", value)
+        }
+    }
+
+    fn not_taken(&self, op: &Op) -> String {
+        let value = operand(op.params[0]);
+        match op.code {
+            InstructionCode::JumpIfTrue => format!("{} == 0", value),
+            _ => format!("{} != 0", value)
+        }
+    }
+
+    fn statement(&mut self, i: usize) -> Option<String> {
+        let op = &self.ops[i];
+        let p = |n: usize| operand(op.params[n]);
+
+        let text = match op.code {
+            InstructionCode::Addition | InstructionCode::Multiplication if self.calls.contains(&(i + 1)) => return None,
+            InstructionCode::Addition => match (op.params[0], op.params[1]) {
+                ((ParameterMode::Immediate, 0), _) => format!("{} = {}", destination(op.params[2]), p(1)),
+                (_, (ParameterMode::Immediate, 0)) => format!("{} = {}", destination(op.params[2]), p(0)),
+                _ => format!("{} = {} + {}", destination(op.params[2]), p(0), p(1))
+            },
+            InstructionCode::Multiplication => match op.params[1] {
+                (ParameterMode::Immediate, 1) => format!("{} = {}", destination(op.params[2]), p(0)),
+                (ParameterMode::Immediate, -1) => format!("{} = -{}", destination(op.params[2]), p(0)),
+                _ => format!("{} = {} * {}", destination(op.params[2]), p(0), p(1))
+            },
+            InstructionCode::LessThan => format!("{} = {} < {}", destination(op.params[2]), p(0), p(1)),
+            InstructionCode::Equals => format!("{} = {} == {}", destination(op.params[2]), p(0), p(1)),
+            InstructionCode::Input => format!("{} = input()", destination(op.params[0])),
+            InstructionCode::Output => format!("output({})", p(0)),
+            InstructionCode::RelativeBaseAdjust => match op.params[0] {
+                (ParameterMode::Immediate, n) if n >= 0 => format!("enter_frame({})", n),
+                (ParameterMode::Immediate, n) => format!("leave_frame({})", n.unsigned_abs()),
+                _ => format!("rb += {}", p(0))
+            },
+            InstructionCode::ReturnFromInterrupt => String::from("return_from_interrupt"),
+            InstructionCode::Finish => String::from("halt"),
+            _ if self.calls.contains(&i) => format!("call {}()", self.jump_target(op)),
+            _ if op.unconditional() && op.params[1].0 == ParameterMode::Relative => String::from("return"),
+            _ => {
+                let target = self.jump_target(op);
+                if let Some(address) = op.target() { self.labels.insert(address); }
+
+                match op.unconditional() {
+                    true => format!("goto {}", target),
+                    false => format!("if ({}) goto {}", self.taken(op), target)
+                }
+            }
+        };
+
+        Some(text)
+    }
+
+    // Lays out ops[from..to], turning jumps that nest cleanly into loops and conditionals
+    fn emit(&mut self, from: usize, to: usize, depth: usize) {
+        let mut i = from;
+
+        while i < to {
+            let address = self.ops[i].address;
+
+            let back = (i..to).rev().find(|&j| {
+                let op = &self.ops[j];
+                op.is_jump() && op.target() == Some(address) && !self.calls.contains(&j)
+            });
+
+            if let Some(j) = back {
+                let (head, tail) = (&self.ops[i], &self.ops[j]);
+
+                if tail.unconditional() && i < j && head.conditional() && head.target() == Some(tail.end()) {
+                    let text = format!("while ({}) {{", self.not_taken(head));
+                    self.push(Some(address), depth, text);
+                    self.emit(i + 1, j, depth + 1);
+                } else if tail.unconditional() {
+                    self.push(Some(address), depth, String::from("loop {"));
+                    self.emit(i, j, depth + 1);
+                } else {
+                    let condition = self.taken(tail);
+                    self.push(Some(address), depth, String::from("do {"));
+                    self.emit(i, j, depth + 1);
+                    self.push(None, depth, format!("}} while ({})", condition));
+                    i = j + 1;
+                    continue;
+                }
+
+                self.push(None, depth, String::from("}"));
+                i = j + 1;
+                continue;
+            }
+
+            let forward = match (self.ops[i].conditional(), self.ops[i].target()) {
+                (true, Some(target)) if target > address => self.index_of(target).filter(|&k| k <= to),
+                _ => None
+            };
+
+            if let Some(k) = forward {
+                let condition = self.not_taken(&self.ops[i]);
+                let last = k - 1;
+                let otherwise = match self.ops[last].target() {
+                    Some(end) if last > i && self.ops[last].unconditional() && !self.calls.contains(&last) && end > self.ops[k].address =>
+                        self.index_of(end).filter(|&m| m <= to),
+                    _ => None
+                };
+
+                self.push(Some(address), depth, format!("if ({}) {{", condition));
+
+                match otherwise {
+                    Some(m) => {
+                        self.emit(i + 1, last, depth + 1);
+                        self.push(None, depth, String::from("} else {"));
+                        self.emit(k, m, depth + 1);
+                        i = m;
+                    },
+                    None => {
+                        self.emit(i + 1, k, depth + 1);
+                        i = k;
+                    }
+                }
+
+                self.push(None, depth, String::from("}"));
+                continue;
+            }
+
+            if let Some(text) = self.statement(i) {
+                self.push(Some(address), depth, text);
+            }
+
+            i += 1;
+        }
+    }
+
+    fn render(mut self) -> String {
+        self.emit(0, self.ops.len(), 1);
+
+        let mut output = String::from("entry:\n");
+        let mut placed: BTreeSet<usize> = BTreeSet::new();
+
+        for line in &self.lines {
+            if let Some(address) = line.address {
+                if placed.insert(address) {
+                    if self.functions.contains(&address) {
+                        output.push_str(&format!("\nfunc_{}:\n", address));
+                    } else if self.labels.contains(&address) {
+                        output.push_str(&format!("label_{}:\n", address));
+                    }
+                }
+            }
+
+            output.push_str(&"    ".repeat(line.depth));
+            output.push_str(&line.text);
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+pub fn decompile(image: &[Word]) -> String {
+    Decompiler::new(image).render()
+}
+
+#[test]
+fn test_if_else() {
+    let image = [3, 100, 1005, 100, 10, 104, 0, 1105, 1, 12, 104, 1, 99];
+
+    assert_eq!(decompile(&image), "entry:
+    mem[100] = input()
+    if (mem[100] == 0) {
+        output(0)
+    } else {
+        output(1)
+    }
+    halt
+");
+}
+
+#[test]
+fn test_while_loop() {
+    let image = [1101, 0, 3, 100, 1006, 100, 16, 4, 100, 1001, 100, -1, 100, 1105, 1, 4, 99];
+
+    assert_eq!(decompile(&image), "entry:
+    mem[100] = 3
+    while (mem[100] != 0) {
+        output(mem[100])
+        mem[100] = mem[100] + -1
+    }
+    halt
+");
+}
+
+#[test]
+fn test_call_and_return() {
+    let image = [21101, 7, 0, 0, 1105, 1, 8, 99, 109, 1, 104, 42, 109, -1, 2106, 0, 0];
+
+    assert_eq!(decompile(&image), "entry:
+    call func_8()
+    halt
+
+func_8:
+    enter_frame(1)
+    output(42)
+    leave_frame(1)
+    return
+");
+}
+
+#[test]
+fn test_any_image() {
+    use crate::device::{Device, Random};
+
+    let words = [0, 1, -1, 2, 99, 98, 1101, 1102, 1105, 1106, 1001, 1002, 21101, 21102, 109, 209, 2106, 203, 204, Word::MIN, Word::MAX];
+    let mut random = Random::new(7);
+
+    for _ in 0..2000 {
+        let len = random.read(0) as usize % 24 + 1;
+        let image: Vec<Word> = (0..len).map(|_| words[random.read(0) as usize % words.len()]).collect();
+        decompile(&image);
+    }
+
+    assert!(decompile(&[109, Word::MIN, 99]).contains(&format!("leave_frame({})", Word::MIN.unsigned_abs())));
+    assert!(decompile(&[2101, 0, Word::MIN, 0, 99]).contains(&format!("mem[0] = arg{}", Word::MIN.unsigned_abs())));
+}
@@ -0,0 +1,210 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+use crate::Word;
+
+// Devices see addresses as offsets from the start of the range they are mapped on
+pub trait Device: Send {
+    fn read(&mut self, offset: usize) -> Word;
+    fn write(&mut self, offset: usize, value: Word);
+    fn tick(&mut self) {}
+
+    // How many offsets the device answers on, or None if it takes any
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Lets the caller keep a handle on a device after handing it to a program
+#[cfg(feature = "std")]
+impl<D: Device> Device for Arc<Mutex<D>> {
+    fn read(&mut self, offset: usize) -> Word {
+        self.lock().unwrap().read(offset)
+    }
+
+    fn write(&mut self, offset: usize, value: Word) {
+        self.lock().unwrap().write(offset, value)
+    }
+
+    fn tick(&mut self) {
+        self.lock().unwrap().tick()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        self.lock().unwrap().capacity()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Clock {
+    ticks: Word
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock { ticks: 0 }
+    }
+}
+
+impl Device for Clock {
+    fn read(&mut self, _offset: usize) -> Word {
+        self.ticks
+    }
+
+    fn write(&mut self, _offset: usize, value: Word) {
+        self.ticks = value;
+    }
+
+    fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero
+        Random { state: if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed } }
+    }
+}
+
+impl Device for Random {
+    fn read(&mut self, _offset: usize) -> Word {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        (self.state >> 1) as Word
+    }
+
+    fn write(&mut self, _offset: usize, value: Word) {
+        *self = Random::new(value as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Word>
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Result<Self, String> {
+        match width.checked_mul(height) {
+            Some(0) => Err(format!("a {}x{} framebuffer has no pixels", width, height)),
+            Some(size) => Ok(Framebuffer { width, height, pixels: vec!(0; size) }),
+            None => Err(format!("a {}x{} framebuffer is too large", width, height))
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Word {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        for row in self.pixels.chunks(self.width) {
+            for pixel in row {
+                output.push(if *pixel == 0 { ' ' } else { '#' });
+            }
+
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: usize) -> Word {
+        self.pixels[offset]
+    }
+
+    fn write(&mut self, offset: usize, value: Word) {
+        self.pixels[offset] = value;
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(self.size())
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_devices() {
+    use crate::Program;
+
+    // Copy the clock into the framebuffer, then draw a pixel at offset 2
+    let mut program = Program::from(String::from("1001,1000,0,2000,1101,0,1,2002,4,1000,99"));
+    let framebuffer = Arc::new(Mutex::new(Framebuffer::new(2, 2).unwrap()));
+
+    program.map_device(1000..1001, Box::new(Clock::new())).unwrap();
+    program.map_device(2000..2004, Box::new(framebuffer.clone())).unwrap();
+
+    assert_eq!(program.run(&mut vec!()), vec!(2));
+    assert_eq!(framebuffer.lock().unwrap().pixel(0, 0), 0);
+    assert_eq!(framebuffer.lock().unwrap().render(), "  \n# \n");
+}
+
+#[test]
+fn test_random_is_seeded() {
+    let mut first = Random::new(42);
+    let mut second = Random::new(42);
+
+    for _ in 0..10 {
+        assert_eq!(first.read(0), second.read(0));
+    }
+}
+
+#[test]
+fn test_map_device_checks_capacity() {
+    use crate::Program;
+
+    let mut program = Program::from(vec!(99));
+    assert_eq!(
+        program.map_device(2000..2010, Box::new(Framebuffer::new(2, 2).unwrap())),
+        Err(String::from("2000..2010 is 10 addresses, but the device only has 4"))
+    );
+    assert!(program.map_device(2000..2002, Box::new(Framebuffer::new(2, 2).unwrap())).is_ok());
+}
+
+#[test]
+fn test_framebuffer_needs_pixels() {
+    assert_eq!(Framebuffer::new(0, 3).unwrap_err(), "a 0x3 framebuffer has no pixels");
+    assert_eq!(Framebuffer::new(usize::MAX, 2).unwrap_err(), format!("a {}x2 framebuffer is too large", usize::MAX));
+}
+
+#[test]
+fn test_map_device_rejects_overlaps() {
+    use crate::Program;
+
+    let mut program = Program::from(vec!(99));
+    program.map_device(1000..1004, Box::new(Clock::new())).unwrap();
+
+    assert_eq!(
+        program.map_device(1003..1005, Box::new(Clock::new())),
+        Err(String::from("1003..1005 overlaps the device already mapped on 1000..1004"))
+    );
+    assert!(program.map_device(1004..1005, Box::new(Clock::new())).is_ok());
+}
+
+#[test]
+fn test_clock_wraps() {
+    let mut clock = Clock::new();
+    clock.write(0, Word::MAX);
+    clock.tick();
+    assert_eq!(clock.read(0), Word::MIN);
+}
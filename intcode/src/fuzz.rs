@@ -0,0 +1,616 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::channel;
+use crate::{InstructionCode, ParameterMode, Program, ProgramError, ProgramResult, SCRATCH, Word};
+use crate::device::{Device, Random};
+use crate::inspect::{diff, report};
+use crate::threaded::{self, ExitStatus};
+
+pub const STEP_LIMIT: u64 = 100_000;
+
+// Generated programs open with a jump over their data, so every address is known up front
+const DATA: usize = 3;
+const DATA_LEN: usize = 16;
+const COUNTERS: usize = DATA + DATA_LEN;
+const MAX_DEPTH: usize = 2;
+const CODE: usize = COUNTERS + MAX_DEPTH;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub image: Vec<Word>,
+    pub inputs: Vec<Word>
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |words: &[Word]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>().join(",");
+        writeln!(f, "program: {}", join(&self.image))?;
+        write!(f, "input: {}", join(&self.inputs))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Complete,
+    Error(ProgramError),
+    StepLimit
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub outputs: Vec<Word>,
+    pub outcome: Outcome,
+    pub steps: u64,
+    pub memory: Vec<Word>
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Mismatch {
+    Panic { engine: &'static str, message: String },
+    Diverged { engine: &'static str, expected: Box<Trace>, found: Box<Trace> }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "outputs {:?}, {:?} after {} steps", self.outputs, self.outcome, self.steps)
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Panic { engine, message } => write!(f, "{} panicked: {}", engine, message),
+            Mismatch::Diverged { engine, expected, found } => {
+                write!(f, "{} diverged from the reference: expected {}, found {}", engine, expected, found)?;
+
+                let changes = diff(&expected.memory, &found.memory);
+                if !changes.is_empty() {
+                    write!(f, "\nmemory differs:\n{}", report(&changes))?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Generator {
+    random: Random,
+    words: Vec<Word>,
+    base: Word,
+    depth: usize,
+    repeat: usize,
+    inputs: usize
+}
+
+impl Generator {
+    fn below(&mut self, n: usize) -> usize {
+        self.random.read(0) as usize % n
+    }
+
+    fn small(&mut self) -> Word {
+        self.below(21) as Word - 10
+    }
+
+    fn cell(&mut self) -> usize {
+        DATA + self.below(DATA_LEN)
+    }
+
+    // Returns the mode digit and the word for an operand that is read
+    fn source(&mut self) -> (Word, Word) {
+        match self.below(3) {
+            0 => (1, self.small()),
+            1 => (0, self.cell() as Word),
+            _ => (2, self.cell() as Word - self.base)
+        }
+    }
+
+    fn destination(&mut self) -> (Word, Word) {
+        match self.below(2) {
+            0 => (0, self.cell() as Word),
+            _ => (2, self.cell() as Word - self.base)
+        }
+    }
+
+    fn emit(&mut self, opcode: Word, operands: &[(Word, Word)]) {
+        let modes = operands.iter().rev().fold(0, |modes, (mode, _)| modes * 10 + mode);
+        self.words.push(modes * 100 + opcode);
+        self.words.extend(operands.iter().map(|(_, word)| word));
+    }
+
+    fn block(&mut self, length: usize) {
+        for _ in 0..length {
+            match self.below(10) {
+                0..=3 => {
+                    let opcode = [1, 2, 7, 8][self.below(4)];
+                    let operands = [self.source(), self.source(), self.destination()];
+                    self.emit(opcode, &operands);
+                },
+                4 => {
+                    let operand = self.destination();
+                    self.emit(3, &[operand]);
+                    self.inputs += self.repeat;
+                },
+                5 | 6 => {
+                    let operand = self.source();
+                    self.emit(4, &[operand]);
+                },
+                // The relative base only moves outside loops and conditionals, so it is
+                // known statically wherever an operand is generated
+                7 if self.depth == 0 => {
+                    let base = self.below(COUNTERS) as Word;
+                    self.emit(9, &[(1, base - self.base)]);
+                    self.base = base;
+                },
+                8 if self.depth < MAX_DEPTH => self.conditional(),
+                9 if self.depth < MAX_DEPTH => self.counted_loop(),
+                _ => {
+                    let operand = self.source();
+                    self.emit(4, &[operand]);
+                }
+            }
+        }
+    }
+
+    // Forward jumps only, over a block that is generated after the jump
+    fn conditional(&mut self) {
+        let opcode = 5 + self.below(2) as Word;
+        let condition = self.source();
+        self.emit(opcode, &[condition, (1, 0)]);
+        let patch = self.words.len() - 1;
+
+        let length = 1 + self.below(3);
+        self.depth += 1;
+        self.block(length);
+        self.depth -= 1;
+
+        self.words[patch] = self.words.len() as Word;
+    }
+
+    // Each nesting level has its own counter that the body never writes to
+    fn counted_loop(&mut self) {
+        let counter = (COUNTERS + self.depth) as Word;
+        let count = 1 + self.below(4) as Word;
+        self.emit(1, &[(1, count), (1, 0), (0, counter)]);
+        let start = self.words.len() as Word;
+
+        let length = 1 + self.below(3);
+        self.depth += 1;
+        self.repeat *= count as usize;
+        self.block(length);
+        self.repeat /= count as usize;
+        self.depth -= 1;
+
+        self.emit(1, &[(0, counter), (1, -1), (0, counter)]);
+        self.emit(5, &[(0, counter), (1, start)]);
+    }
+}
+
+// Loops are counted and jumps only go forward otherwise, so these programs always halt
+pub fn generate(seed: u64, length: usize) -> Case {
+    let mut generator = Generator {
+        random: Random::new(seed),
+        words: vec!(1105, 1, CODE as Word),
+        base: 0,
+        depth: 0,
+        repeat: 1,
+        inputs: 0
+    };
+
+    for _ in DATA..CODE {
+        let value = generator.small();
+        generator.words.push(value);
+    }
+
+    generator.block(length);
+    generator.emit(99, &[]);
+
+    let inputs = (0..generator.inputs).map(|_| generator.small()).collect();
+    Case { image: generator.words, inputs }
+}
+
+// Overwrites a few words with values the generator never produces, including bad opcodes,
+// modes and addresses
+pub fn mutate(case: &Case, seed: u64) -> Case {
+    let mut random = Random::new(seed);
+    let mut image = case.image.clone();
+
+    for _ in 0..1 + random.read(0) % 3 {
+        let address = random.read(0) as usize % image.len();
+        let value = random.read(0);
+
+        image[address] = match value % 5 {
+            0 => value % 100,
+            1 => value % 100_000,
+            2 => -(value % 1000),
+            3 => value,
+            _ => 99
+        };
+    }
+
+    Case { image, inputs: case.inputs.clone() }
+}
+
+fn program(case: &Case) -> Program {
+    Program::from(case.image.clone())
+}
+
+fn trace(program: &Program, outputs: Vec<Word>, outcome: Outcome) -> Trace {
+    Trace { outputs, outcome, steps: program.steps(), memory: program.memory().to_vec() }
+}
+
+// Feeds inputs one at a time and stops after STEP_LIMIT instructions
+pub fn reference(case: &Case) -> Trace {
+    let mut program = program(case);
+    let mut inputs = case.inputs.iter().copied();
+    let mut outputs = Vec::new();
+
+    let outcome = loop {
+        if program.steps() >= STEP_LIMIT { break Outcome::StepLimit; }
+
+        if program.awaiting_input() {
+            if let Some(value) = inputs.next() { program.append_inputs(&mut vec!(value)); }
+        }
+
+        match program.try_step() {
+            Ok(Some(ProgramResult::Output(value))) => outputs.push(value),
+            Ok(Some(ProgramResult::Complete)) => break Outcome::Complete,
+            Ok(None) => (),
+            Err(error) => break Outcome::Error(error)
+        }
+    };
+
+    trace(&program, outputs, outcome)
+}
+
+pub fn event_loop(case: &Case) -> Trace {
+    let mut program = program(case);
+    let mut outputs = Vec::new();
+    program.append_inputs(&mut case.inputs.clone());
+
+    let outcome = loop {
+        match program.try_run_until_event() {
+            Ok(ProgramResult::Output(value)) => outputs.push(value),
+            Ok(ProgramResult::Complete) => break Outcome::Complete,
+            Err(error) => break Outcome::Error(error)
+        }
+    };
+
+    trace(&program, outputs, outcome)
+}
+
+pub fn iterator(case: &Case) -> Trace {
+    let mut program = program(case);
+    let mut inputs = case.inputs.iter().copied();
+    let mut outputs = Vec::new();
+    let mut outcome = Outcome::Complete;
+
+    for output in program.outputs(|| inputs.next()) {
+        match output {
+            Ok(value) => outputs.push(value),
+            Err(error) => outcome = Outcome::Error(error)
+        }
+    }
+
+    trace(&program, outputs, outcome)
+}
+
+// All inputs are queued and the sender dropped up front, so running out closes the input
+pub fn threaded(case: &Case) -> Trace {
+    let (input, inputs) = channel();
+    let (sender, output) = channel();
+
+    for value in &case.inputs {
+        input.send(*value).unwrap();
+    }
+    drop(input);
+
+    let handle = threaded::spawn_connected(program(case), inputs, sender);
+    let outputs: Vec<Word> = output.iter().collect();
+    let halted = handle.join().unwrap();
+    let outcome = match halted.status {
+        ExitStatus::Complete => Outcome::Complete,
+        ExitStatus::InputClosed { pc } => Outcome::Error(ProgramError::NoInput { pc }),
+        ExitStatus::Error(error) => Outcome::Error(error)
+    };
+
+    Trace { outputs, outcome, steps: halted.steps, memory: halted.memory }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Decoded {
+    code: InstructionCode,
+    modes: [ParameterMode; 3],
+    operands: [Option<Word>; 3]
+}
+
+// An executor written apart from Program. Each instruction is decoded the first time it runs
+// and kept until one of its words is written.
+struct Decoder {
+    memory: Vec<Word>,
+    cache: Vec<Option<Decoded>>,
+    inputs: VecDeque<Word>,
+    pc: usize,
+    relative_base: Word,
+    steps: u64
+}
+
+impl Decoder {
+    fn decode(&mut self) -> Result<Decoded, ProgramError> {
+        let pc = self.pc;
+        if let Some(decoded) = self.cache.get(pc).copied().flatten() {
+            return Ok(decoded);
+        }
+
+        let value = *self.memory.get(pc).ok_or(ProgramError::AddressOutOfRange { pc, address: pc as Word })?;
+        let code = InstructionCode::try_from(value % 100).map_err(|_| ProgramError::InvalidOpcode { pc, value })?;
+        let decoded = Decoded {
+            code,
+            modes: [100, 1000, 10000].map(|scale| match value / scale % 10 {
+                1 => ParameterMode::Immediate,
+                2 => ParameterMode::Relative,
+                _ => ParameterMode::Position
+            }),
+            operands: [1, 2, 3].map(|offset| self.memory.get(pc + offset).copied())
+        };
+
+        self.cache[pc] = Some(decoded);
+        Ok(decoded)
+    }
+
+    fn address(&self, address: Word) -> Result<usize, ProgramError> {
+        usize::try_from(address).ok()
+            .filter(|a| *a < self.memory.len())
+            .ok_or(ProgramError::AddressOutOfRange { pc: self.pc, address })
+    }
+
+    // Where operand `index` points, for modes that point somewhere
+    fn target(&self, decoded: &Decoded, index: usize) -> Result<usize, ProgramError> {
+        let pc = self.pc;
+        let value = decoded.operands[index].ok_or(ProgramError::AddressOutOfRange { pc, address: (pc + index + 1) as Word })?;
+
+        match decoded.modes[index] {
+            ParameterMode::Relative => self.address(value.checked_add(self.relative_base).ok_or(ProgramError::Overflow { pc })?),
+            _ => self.address(value)
+        }
+    }
+
+    fn read(&self, decoded: &Decoded, index: usize) -> Result<Word, ProgramError> {
+        match (decoded.modes[index], decoded.operands[index]) {
+            (ParameterMode::Immediate, Some(value)) => Ok(value),
+            _ => self.target(decoded, index).map(|address| self.memory[address])
+        }
+    }
+
+    // Drops every cached instruction the word could be part of
+    fn write(&mut self, address: usize, value: Word) {
+        self.memory[address] = value;
+
+        for entry in &mut self.cache[address.saturating_sub(3)..=address] {
+            *entry = None;
+        }
+    }
+
+    // Ok(true) once the program halts
+    fn step(&mut self, outputs: &mut Vec<Word>) -> Result<bool, ProgramError> {
+        let decoded = self.decode()?;
+        let pc = self.pc;
+        let overflow = ProgramError::Overflow { pc };
+
+        let next = match decoded.code {
+            InstructionCode::Addition | InstructionCode::Multiplication | InstructionCode::LessThan | InstructionCode::Equals => {
+                let (a, b) = (self.read(&decoded, 0)?, self.read(&decoded, 1)?);
+                let destination = self.target(&decoded, 2)?;
+                let result = match decoded.code {
+                    InstructionCode::Addition => a.checked_add(b).ok_or(overflow)?,
+                    InstructionCode::Multiplication => a.checked_mul(b).ok_or(overflow)?,
+                    InstructionCode::LessThan => (a < b) as Word,
+                    _ => (a == b) as Word
+                };
+
+                self.write(destination, result);
+                pc + 4
+            },
+            InstructionCode::Input => {
+                let value = *self.inputs.front().ok_or(ProgramError::NoInput { pc })?;
+                let destination = self.target(&decoded, 0)?;
+
+                self.inputs.pop_front();
+                self.write(destination, value);
+                pc + 2
+            },
+            InstructionCode::Output => {
+                outputs.push(self.read(&decoded, 0)?);
+                pc + 2
+            },
+            InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse => {
+                let condition = self.read(&decoded, 0)?;
+                let target = self.read(&decoded, 1)?;
+
+                match (condition != 0) == (decoded.code == InstructionCode::JumpIfTrue) {
+                    true => usize::try_from(target).map_err(|_| ProgramError::AddressOutOfRange { pc, address: target })?,
+                    false => pc + 3
+                }
+            },
+            InstructionCode::RelativeBaseAdjust => {
+                self.relative_base = self.relative_base.checked_add(self.read(&decoded, 0)?).ok_or(overflow)?;
+                pc + 2
+            },
+            // Nothing raises interrupts here, so there is never a handler to return from
+            InstructionCode::ReturnFromInterrupt => return Err(ProgramError::InvalidOpcode { pc, value: self.memory[pc] }),
+            InstructionCode::Finish => {
+                self.steps += 1;
+                return Ok(true);
+            }
+        };
+
+        self.steps += 1;
+        self.pc = next;
+        Ok(false)
+    }
+}
+
+pub fn decoded(case: &Case) -> Trace {
+    let mut memory = case.image.clone();
+    memory.resize(case.image.len() + SCRATCH, 0);
+
+    let mut decoder = Decoder {
+        cache: vec!(None; memory.len()),
+        memory,
+        inputs: case.inputs.iter().copied().collect(),
+        pc: 0,
+        relative_base: 0,
+        steps: 0
+    };
+    let mut outputs = Vec::new();
+
+    let outcome = loop {
+        match decoder.step(&mut outputs) {
+            Ok(true) => break Outcome::Complete,
+            Ok(false) => (),
+            Err(error) => break Outcome::Error(error)
+        }
+    };
+
+    Trace { outputs, outcome, steps: decoder.steps, memory: decoder.memory }
+}
+
+// The first three drive the one interpreter through different front ends, catching bugs in
+// the drivers and the error paths. The decoder executes on its own, so it checks the
+// interpreter itself.
+pub type Engine = fn(&Case) -> Trace;
+
+pub const ENGINES: &[(&str, Engine)] = &[
+    ("event loop", event_loop),
+    ("iterator", iterator),
+    ("threaded", threaded),
+    ("decoder", decoded)
+];
+
+fn guarded(engine: &'static str, run: Engine, case: &Case) -> Result<Trace, Mismatch> {
+    panic::catch_unwind(AssertUnwindSafe(|| run(case))).map_err(|payload| {
+        let message = payload.downcast_ref::<String>().cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        Mismatch::Panic { engine, message }
+    })
+}
+
+// The engines only run once the reference has finished, since they have no step limit
+pub fn check(case: &Case) -> Result<Trace, Mismatch> {
+    let expected = guarded("reference", reference, case)?;
+    if expected.outcome == Outcome::StepLimit { return Ok(expected); }
+
+    for (engine, run) in ENGINES {
+        let found = guarded(engine, *run, case)?;
+        if found != expected {
+            return Err(Mismatch::Diverged { engine, expected: Box::new(expected), found: Box::new(found) });
+        }
+    }
+
+    Ok(expected)
+}
+
+fn candidates(case: &Case) -> Vec<Case> {
+    let mut candidates = Vec::new();
+    let with_image = |image: Vec<Word>| Case { image, inputs: case.inputs.clone() };
+
+    for len in [case.image.len() / 2, case.image.len().saturating_sub(1)] {
+        if len > 0 { candidates.push(with_image(case.image[..len].to_vec())); }
+    }
+
+    // Runs as long as an instruction, so whole instructions can go at once
+    for length in (1..=4).rev() {
+        if case.image.len() <= length { continue; }
+
+        for address in 0..=case.image.len() - length {
+            let mut image = case.image.clone();
+            image.drain(address..address + length);
+            candidates.push(with_image(image));
+        }
+    }
+
+    if !case.inputs.is_empty() {
+        candidates.push(Case { image: case.image.clone(), inputs: case.inputs[..case.inputs.len() / 2].to_vec() });
+    }
+
+    for address in (0..case.image.len()).rev() {
+        for value in [99, 0, 1, case.image[address] / 2] {
+            if value.unsigned_abs() < case.image[address].unsigned_abs() {
+                let mut image = case.image.clone();
+                image[address] = value;
+                candidates.push(with_image(image));
+            }
+        }
+    }
+
+    candidates
+}
+
+// Greedily truncates the image, removes runs of words, drops inputs and shrinks words towards
+// halts and zeros, keeping each change that still fails
+pub fn shrink<F: Fn(&Case) -> bool>(case: &Case, fails: F) -> Case {
+    let mut smallest = case.clone();
+
+    while let Some(smaller) = candidates(&smallest).into_iter().find(|c| fails(c)) {
+        smallest = smaller;
+    }
+
+    smallest
+}
+
+// Half the cases are mutated so the typed error paths get exercised too
+pub fn fuzz(seed: u64, iterations: u64) -> Result<(), (Case, Mismatch)> {
+    for iteration in 0..iterations {
+        let seed = seed.wrapping_add(iteration);
+        let case = generate(seed, 4 + (seed % 8) as usize);
+        let case = if iteration % 2 == 0 { case } else { mutate(&case, seed) };
+
+        if check(&case).is_err() {
+            let minimal = shrink(&case, |c| check(c).is_err());
+            let mismatch = check(&minimal).unwrap_err();
+            return Err((minimal, mismatch));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_generated_programs_halt() {
+    use crate::lint::lint;
+
+    for seed in 0..200 {
+        let case = generate(seed, 10);
+        let trace = reference(&case);
+
+        assert_eq!(lint(&case.image), vec!(), "seed {}", seed);
+        assert!(matches!(trace.outcome, Outcome::Complete | Outcome::Error(ProgramError::Overflow { .. })), "seed {}: {:?}", seed, trace.outcome);
+    }
+}
+
+#[test]
+fn test_engines_agree() {
+    if let Err((case, mismatch)) = fuzz(1, 500) {
+        panic!("{}\n{}", mismatch, case);
+    }
+}
+
+#[test]
+fn test_decoder_sees_self_modifying_code() {
+    // Bumps the operand of the output at 0 until it reaches 7
+    let case = Case { image: vec!(104, 5, 1001, 1, 1, 1, 1007, 1, 7, 14, 1005, 14, 0, 99, 0), inputs: vec!() };
+
+    assert_eq!(decoded(&case).outputs, vec!(5, 6));
+    assert_eq!(decoded(&case), reference(&case));
+}
+
+#[test]
+fn test_shrink() {
+    let case = Case { image: vec!(1101, 3, 4, 20, 104, 7, 4, 20, 99), inputs: vec!(1, 2, 3) };
+    let outputs_seven = |c: &Case| reference(c).outputs.contains(&7);
+
+    assert_eq!(shrink(&case, outputs_seven), Case { image: vec!(104, 7), inputs: vec!() });
+}
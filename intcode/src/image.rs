@@ -0,0 +1,162 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use crate::{Program, SCRATCH, Word};
+use crate::parse::{self, ParseError};
+
+// A parsed program that can be instantiated any number of times. Instances share the
+// image until they first write to memory, so running many copies of a program skips both
+// the parse and, for programs that never write, the copy.
+#[derive(Debug, Clone)]
+pub struct ProgramImage {
+    words: Arc<Vec<Word>>,
+    len: usize
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedMemory {
+    initial: Arc<Vec<Word>>,
+    words: Arc<Vec<Word>>
+}
+
+impl AsRef<[Word]> for SharedMemory {
+    fn as_ref(&self) -> &[Word] {
+        &self.words
+    }
+}
+
+// The first write takes a private copy, later ones go straight to it
+impl AsMut<[Word]> for SharedMemory {
+    fn as_mut(&mut self) -> &mut [Word] {
+        Arc::make_mut(&mut self.words).as_mut_slice()
+    }
+}
+
+impl SharedMemory {
+    fn new(initial: Arc<Vec<Word>>) -> Self {
+        SharedMemory { words: Arc::clone(&initial), initial }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        Arc::ptr_eq(&self.initial, &self.words)
+    }
+}
+
+impl From<Vec<Word>> for ProgramImage {
+    fn from(mut words: Vec<Word>) -> Self {
+        let len = words.len();
+        words.resize(len + SCRATCH, 0);
+
+        ProgramImage { words: Arc::new(words), len }
+    }
+}
+
+// Keeps the program's own words as they are now, so a program built with `intcode!` can be
+// shared without going back through a word list
+impl From<Program> for ProgramImage {
+    fn from(program: Program) -> Self {
+        let len = program.image().len();
+        let mut words = program.memory;
+        words.truncate(len);
+
+        ProgramImage::from(words)
+    }
+}
+
+impl FromStr for ProgramImage {
+    type Err = ParseError;
+
+    fn from_str(program_string: &str) -> Result<Self, Self::Err> {
+        parse::parse(program_string).map(ProgramImage::from)
+    }
+}
+
+impl ProgramImage {
+    pub fn image(&self) -> &[Word] {
+        &self.words[..self.len]
+    }
+
+    pub fn instantiate(&self) -> Program<SharedMemory> {
+        self.program(Arc::clone(&self.words))
+    }
+
+    // Patches are written once here and survive `reset`. Addresses past the end of memory
+    // are ignored, as they would be out of range for the program anyway.
+    pub fn instantiate_with(&self, patches: &[(usize, Word)]) -> Program<SharedMemory> {
+        if patches.is_empty() { return self.instantiate(); }
+
+        let mut words = Vec::clone(&self.words);
+        for (address, value) in patches {
+            if let Some(word) = words.get_mut(*address) { *word = *value; }
+        }
+
+        self.program(Arc::new(words))
+    }
+
+    fn program(&self, initial: Arc<Vec<Word>>) -> Program<SharedMemory> {
+        Program {
+            memory: SharedMemory::new(initial),
+            pc: 0,
+            inputs: Vec::new(),
+            relative_base: 0,
+            steps: 0,
+            image_len: self.len,
+            devices: Vec::new(),
+            interrupts: None
+        }
+    }
+}
+
+impl Program<SharedMemory> {
+    // Back to the state it was instantiated in, dropping any private copy of memory,
+    // pending inputs and pending interrupts. Attached devices and interrupt settings stay.
+    pub fn reset(&mut self) {
+        self.memory.words = Arc::clone(&self.memory.initial);
+        self.pc = 0;
+        self.inputs.clear();
+        self.relative_base = 0;
+        self.steps = 0;
+        if let Some(interrupts) = &mut self.interrupts { interrupts.clear(); }
+    }
+}
+
+#[test]
+fn test_instances_share_until_written() {
+    let image: ProgramImage = "3,9,8,9,10,9,4,9,99,-1,8".parse().unwrap();
+    let mut first = image.instantiate();
+    let second = image.instantiate();
+
+    assert!(first.memory.is_shared() && second.memory.is_shared());
+    assert_eq!(first.run(&mut vec!(8)), vec!(1));
+    assert!(!first.memory.is_shared() && second.memory.is_shared());
+
+    first.reset();
+    assert!(first.memory.is_shared());
+    assert_eq!(first.steps(), 0);
+    assert_eq!(first.run(&mut vec!(7)), vec!(0));
+}
+
+#[test]
+fn test_patches_survive_reset() {
+    let image = ProgramImage::from(vec!(1, 0, 0, 0, 99));
+    let mut program = image.instantiate_with(&[(1, 4), (2, 4)]);
+
+    program.run(&mut vec!());
+    assert_eq!(program.image(), &[198, 4, 4, 0, 99][..]);
+
+    program.reset();
+    assert_eq!(program.image(), &[1, 4, 4, 0, 99][..]);
+    assert_eq!(image.image(), &[1, 0, 0, 0, 99][..]);
+}
+
+#[test]
+fn test_from_program() {
+    use crate::intcode;
+
+    let image = ProgramImage::from(intcode![1101, 2, 3, 0, 99]);
+    assert_eq!(image.image(), &[1101, 2, 3, 0, 99][..]);
+
+    let mut program = image.instantiate();
+    program.run(&mut vec!());
+    assert_eq!(program.image()[0], 5);
+}
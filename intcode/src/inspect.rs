@@ -0,0 +1,199 @@
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Range;
+use crate::{InstructionCode, Word};
+use crate::lint::walk;
+
+pub const COLUMNS: usize = 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Change {
+    pub address: usize,
+    pub old: Word,
+    pub new: Word
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.address, self.old, self.new)
+    }
+}
+
+// Memory past the end of the shorter snapshot reads as zero, like unused VM memory
+pub fn diff(before: &[Word], after: &[Word]) -> Vec<Change> {
+    let len = before.len().max(after.len());
+
+    (0..len).filter_map(|address| {
+        let old = before.get(address).copied().unwrap_or(0);
+        let new = after.get(address).copied().unwrap_or(0);
+        if old == new { None } else { Some(Change { address, old, new }) }
+    }).collect()
+}
+
+pub fn ranges(changes: &[Change]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for change in changes {
+        match ranges.last_mut() {
+            Some(range) if range.end == change.address => range.end += 1,
+            _ => ranges.push(change.address..change.address + 1)
+        }
+    }
+
+    ranges
+}
+
+pub fn report(changes: &[Change]) -> String {
+    let mut output = String::new();
+
+    for range in ranges(changes) {
+        output.push_str(&format!("{}..{} ({} changed)\n", range.start, range.end, range.len()));
+
+        for change in changes.iter().filter(|c| range.contains(&c.address)) {
+            output.push_str(&format!("    {}\n", change));
+        }
+    }
+
+    output
+}
+
+fn mnemonic(code: InstructionCode) -> &'static str {
+    match code {
+        InstructionCode::Addition => "add",
+        InstructionCode::Multiplication => "mul",
+        InstructionCode::Input => "in",
+        InstructionCode::Output => "out",
+        InstructionCode::JumpIfTrue => "jt",
+        InstructionCode::JumpIfFalse => "jf",
+        InstructionCode::LessThan => "lt",
+        InstructionCode::Equals => "eq",
+        InstructionCode::RelativeBaseAdjust => "arb",
+        InstructionCode::ReturnFromInterrupt => "rfi",
+        InstructionCode::Finish => "hlt"
+    }
+}
+
+// A single instruction in the same syntax `intcode!` takes, and how many words it covers.
+// Anything that does not decode is shown as one word of data.
+pub fn disassemble(memory: &[Word], address: usize) -> (String, usize) {
+    let value = memory.get(address).copied().unwrap_or(0);
+    let code = match InstructionCode::try_from(value % 100) {
+        Ok(code) => code,
+        Err(_) => return (format!("data({})", value), 1)
+    };
+
+    let operands: Vec<String> = (1..code.length())
+        .map(|parameter| {
+            let word = memory.get(address + parameter).copied().unwrap_or(0);
+            match (value / Word::pow(10, parameter as u32 + 1)) % 10 {
+                0 => format!("[{}]", word),
+                2 if word == 0 => String::from("[rb]"),
+                2 if word < 0 => format!("[rb - {}]", word.unsigned_abs()),
+                2 => format!("[rb + {}]", word),
+                _ => word.to_string()
+            }
+        })
+        .collect();
+
+    (format!("{}({})", mnemonic(code), operands.join(", ")), code.length())
+}
+
+// One row per COLUMNS words. Changed words are starred, and each row ends with the
+// instructions that start inside it. Code is found by walking the memory as it was before
+// the changes, since a run may have overwritten its own opcodes.
+pub fn dump(memory: &[Word], addresses: Range<usize>, changes: &[Change]) -> String {
+    let addresses = addresses.start.min(memory.len())..addresses.end.min(memory.len());
+    let changed: BTreeSet<usize> = changes.iter().map(|c| c.address).collect();
+
+    let mut before = memory.to_vec();
+    for change in changes {
+        if let Some(word) = before.get_mut(change.address) { *word = change.old; }
+    }
+    let code = walk(&before, vec!(0));
+
+    let width = memory[addresses.clone()].iter().map(|v| v.to_string().len()).max().unwrap_or(1);
+    let address_width = addresses.end.saturating_sub(1).to_string().len();
+    let mut output = String::new();
+
+    let mut start = addresses.start;
+    while start < addresses.end {
+        let end = (start + COLUMNS).min(addresses.end);
+        output.push_str(&format!("{:>w$}:", start, w = address_width));
+
+        for (address, value) in memory.iter().enumerate().take(end).skip(start) {
+            let mark = if changed.contains(&address) { '*' } else { ' ' };
+            output.push_str(&format!(" {:>w$}{}", value, mark, w = width));
+        }
+
+        let hints: Vec<String> = code.range(start..end)
+            .filter_map(|(address, code)| code.map(|c| format!("{}:{}", address, mnemonic(c))))
+            .collect();
+
+        if !hints.is_empty() {
+            let padding = (COLUMNS - (end - start)) * (width + 2);
+            output.push_str(&format!("{}  | {}", " ".repeat(padding), hints.join(" ")));
+        }
+
+        output.push('\n');
+        start = end;
+    }
+
+    output
+}
+
+// Pages are numbered from zero and hold `rows` rows each
+pub fn page(memory: &[Word], changes: &[Change], number: usize, rows: usize) -> Result<String, String> {
+    let size = rows.checked_mul(COLUMNS).ok_or(format!("{} rows is too many for a page", rows))?;
+    let start = number.checked_mul(size).filter(|start| start.checked_add(size).is_some())
+        .ok_or(format!("page {} is past the end of memory", number))?;
+
+    Ok(dump(memory, start..start + size, changes))
+}
+
+#[test]
+fn test_diff() {
+    use crate::Program;
+
+    let mut program = Program::from(String::from("1,9,10,3,2,3,11,0,99,30,40,50"));
+    let before = program.memory().to_vec();
+    program.run(&mut vec!());
+
+    let changes = diff(&before, program.memory());
+    assert_eq!(changes, vec!(
+        Change { address: 0, old: 1, new: 3500 },
+        Change { address: 3, old: 3, new: 70 }
+    ));
+    assert_eq!(ranges(&[Change { address: 4, old: 0, new: 1 }, Change { address: 5, old: 0, new: 2 }]), vec!(4..6));
+    assert_eq!(report(&changes), "0..1 (1 changed)\n    0: 1 -> 3500\n3..4 (1 changed)\n    3: 3 -> 70\n");
+}
+
+#[test]
+fn test_dump() {
+    let before = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    let after = [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
+    let changes = diff(&before, &after);
+
+    assert_eq!(dump(&after, 0..12, &changes), concat!(
+        " 0: 3500*    9    10    70*    2     3    11     0   | 0:add 4:mul\n",
+        " 8:   99    30    40    50                           | 8:hlt\n"
+    ));
+    assert_eq!(page(&after, &changes, 1, 1), Ok(String::from(" 8: 99  30  40  50                   | 8:hlt\n")));
+    assert_eq!(page(&after, &changes, usize::MAX, 16), Err(format!("page {} is past the end of memory", usize::MAX)));
+    assert_eq!(page(&after, &changes, 0, usize::MAX), Err(format!("{} rows is too many for a page", usize::MAX)));
+}
+
+#[test]
+fn test_disassemble() {
+    let memory = [21008, 9, 8, -10, 204, 0, 99, -1];
+
+    assert_eq!(disassemble(&memory, 0), (String::from("eq([9], 8, [rb - 10])"), 4));
+    assert_eq!(disassemble(&memory, 4), (String::from("out([rb])"), 2));
+    assert_eq!(disassemble(&memory, 6), (String::from("hlt()"), 1));
+    assert_eq!(disassemble(&memory, 7), (String::from("data(-1)"), 1));
+    assert_eq!(disassemble(&[204, Word::MIN], 0), (format!("out([rb - {}])", Word::MIN.unsigned_abs()), 2));
+}
@@ -0,0 +1,200 @@
+use alloc::collections::VecDeque;
+use crate::{Memory, Program, ProgramError, Word};
+use crate::observer::Observer;
+
+// Only executes inside a handler, so ordinary programs still see it as invalid
+pub const RETURN_FROM_INTERRUPT: Word = 98;
+
+#[derive(Debug, Clone)]
+struct Timer {
+    period: u64,
+    remaining: u64,
+    line: Word
+}
+
+// One handler serves every line: the line number is written to `cause` before jumping
+// there. Handlers do not nest, so anything raised while one runs waits for its return,
+// which restores the pc and relative base from when the interrupt was taken.
+#[derive(Debug, Clone)]
+pub struct Interrupts {
+    handler: usize,
+    cause: usize,
+    pending: VecDeque<Word>,
+    saved: Option<(usize, Word)>,
+    timer: Option<Timer>
+}
+
+impl Interrupts {
+    // A line that is already waiting is not queued again, so a timer that fires faster than
+    // its handler runs cannot pile up interrupts
+    fn raise(&mut self, line: Word) {
+        if !self.pending.contains(&line) {
+            self.pending.push_back(line);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+        self.saved = None;
+        if let Some(timer) = &mut self.timer { timer.remaining = timer.period; }
+    }
+}
+
+impl<M: Memory> Program<M> {
+    pub fn enable_interrupts(&mut self, handler: usize, cause: usize) {
+        self.interrupts = Some(Interrupts { handler, cause, pending: VecDeque::new(), saved: None, timer: None });
+    }
+
+    // Lines raised before interrupts are enabled are dropped, as are lines already waiting
+    pub fn raise_interrupt(&mut self, line: Word) {
+        if let Some(interrupts) = &mut self.interrupts {
+            interrupts.raise(line);
+        }
+    }
+
+    // Raises `line` every `period` steps from now. A period of zero stops the timer.
+    pub fn set_timer(&mut self, period: u64, line: Word) {
+        if let Some(interrupts) = &mut self.interrupts {
+            interrupts.timer = if period == 0 { None } else { Some(Timer { period, remaining: period, line }) };
+        }
+    }
+
+    pub fn in_interrupt(&self) -> bool {
+        self.interrupts.as_ref().is_some_and(|interrupts| interrupts.saved.is_some())
+    }
+
+    // Taken between instructions, so a taken interrupt stays taken even if the handler's
+    // first instruction then fails
+    pub(crate) fn dispatch_interrupt<O: Observer<M>>(&mut self, observer: &mut O) -> Result<(), ProgramError> {
+        let (line, handler, cause) = match &mut self.interrupts {
+            Some(interrupts) if interrupts.saved.is_none() => match interrupts.pending.pop_front() {
+                Some(line) => (line, interrupts.handler, interrupts.cause),
+                None => return Ok(())
+            },
+            _ => return Ok(())
+        };
+
+        let pc = self.pc;
+        let cause = match self.address(pc, cause as Word) {
+            Ok(cause) => cause,
+            Err(error) => {
+                if let Some(interrupts) = &mut self.interrupts { interrupts.pending.push_front(line); }
+                return Err(error);
+            }
+        };
+
+        observer.on_interrupt(line, pc);
+        self.store_result(observer, line, cause);
+
+        if let Some(interrupts) = &mut self.interrupts { interrupts.saved = Some((pc, self.relative_base)); }
+        self.pc = handler;
+
+        Ok(())
+    }
+
+    // Only decoded inside a handler, so there is always something saved. Returns where to
+    // resume.
+    pub(crate) fn return_from_interrupt<O: Observer<M>>(&mut self, observer: &mut O) -> usize {
+        let (pc, base) = self.interrupts.as_mut()
+            .and_then(|interrupts| interrupts.saved.take())
+            .expect("return from interrupt outside a handler");
+
+        if base != self.relative_base {
+            observer.on_relative_base(self.relative_base, base);
+            self.relative_base = base;
+        }
+
+        pc
+    }
+
+    pub(crate) fn tick_timer(&mut self) {
+        let interrupts = match &mut self.interrupts {
+            Some(interrupts) => interrupts,
+            None => return
+        };
+
+        if let Some(timer) = &mut interrupts.timer {
+            timer.remaining -= 1;
+            if timer.remaining == 0 {
+                timer.remaining = timer.period;
+                let line = timer.line;
+                interrupts.raise(line);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_timer_and_host_interrupts() {
+    use crate::ProgramResult;
+
+    // Counts at 100 forever, while the handler reports each interrupt's line
+    let mut program = Program::from(vec!(1001, 100, 1, 100, 1105, 1, 0, 4, 101, 98));
+    program.enable_interrupts(7, 101);
+    program.set_timer(10, 1);
+    program.raise_interrupt(5);
+
+    assert_eq!(program.run_until_event(), ProgramResult::Output(5));
+    assert!(program.in_interrupt());
+    assert_eq!(program.run_until_event(), ProgramResult::Output(1));
+    assert_eq!(program.steps(), 11);
+    assert_eq!(program.run_until_event(), ProgramResult::Output(1));
+    assert_eq!(program.steps(), 21);
+    assert_eq!((program.in_interrupt(), program.pc()), (true, 9));
+    assert_eq!(program.memory()[100..102], [8, 1]);
+}
+
+#[test]
+fn test_pending_lines_do_not_pile_up() {
+    // The handler at 4 never returns, so the timer keeps firing into a full queue
+    let mut program = Program::from(vec!(1105, 1, 0, 0, 1105, 1, 4));
+    program.enable_interrupts(4, 3);
+    program.set_timer(1, 1);
+
+    for _ in 0..100 {
+        program.step();
+    }
+    program.raise_interrupt(1);
+    program.raise_interrupt(2);
+
+    assert!(program.in_interrupt());
+    assert_eq!(program.interrupts.as_ref().map(|interrupts| interrupts.pending.len()), Some(2));
+}
+
+#[test]
+fn test_return_outside_handler() {
+    let mut program = Program::from(vec!(98));
+    assert_eq!(program.try_step(), Err(ProgramError::InvalidOpcode { pc: 0, value: 98 }));
+
+    program.enable_interrupts(0, 1);
+    assert_eq!(program.try_step(), Err(ProgramError::InvalidOpcode { pc: 0, value: 98 }));
+    assert_eq!(program.steps(), 0);
+}
+
+#[test]
+fn test_handler_restores_relative_base() {
+    use alloc::vec::Vec;
+    use crate::{InstructionCode, ProgramResult};
+
+    struct Codes(Vec<InstructionCode>);
+
+    impl<M> Observer<M> for Codes {
+        fn before_instruction(&mut self, _program: &Program<M>, code: InstructionCode) {
+            self.0.push(code);
+        }
+    }
+
+    // The handler moves the relative base before reporting its line
+    let mut program = Program::from(vec!(1001, 100, 1, 100, 1105, 1, 0, 109, 50, 4, 101, 98));
+    let mut codes = Codes(Vec::new());
+    program.enable_interrupts(7, 101);
+    program.raise_interrupt(3);
+
+    assert_eq!(program.try_step_with(&mut codes), Ok(None));
+    assert_eq!(program.relative_base(), 50);
+    assert_eq!(program.try_step_with(&mut codes), Ok(Some(ProgramResult::Output(3))));
+    assert_eq!(program.try_step_with(&mut codes), Ok(None));
+
+    assert_eq!((program.in_interrupt(), program.pc(), program.relative_base()), (false, 0, 0));
+    assert_eq!(codes.0, vec!(InstructionCode::RelativeBaseAdjust, InstructionCode::Output, InstructionCode::ReturnFromInterrupt));
+}
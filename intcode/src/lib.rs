@@ -1,4 +1,45 @@
-use std::convert::TryFrom;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::ops::Range;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+#[cfg(feature = "std")]
+pub mod binary;
+pub mod callstack;
+#[cfg(feature = "std")]
+pub mod conformance;
+pub mod coverage;
+pub mod decompile;
+pub mod device;
+#[cfg(feature = "std")]
+pub mod fuzz;
+pub mod image;
+pub mod inspect;
+pub mod interrupt;
+pub mod lint;
+pub mod macros;
+pub mod observer;
+pub mod outputs;
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod threaded;
+
+use device::Device;
+use interrupt::{Interrupts, RETURN_FROM_INTERRUPT};
+use observer::Observer;
+use outputs::Outputs;
+use parse::ParseError;
 
 #[derive(Debug)]
 pub struct Instruction {
@@ -6,8 +47,8 @@ pub struct Instruction {
     modes: ParameterExtension
 }
 
-impl From<i64> for Instruction {
-    fn from(instruction: i64) -> Instruction {
+impl From<Word> for Instruction {
+    fn from(instruction: Word) -> Instruction {
         let code = instruction % 100;
         let extensions = instruction / 100;
 
@@ -18,7 +59,7 @@ impl From<i64> for Instruction {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum InstructionCode {
     Addition,
     Multiplication,
@@ -29,13 +70,14 @@ pub enum InstructionCode {
     LessThan,
     Equals,
     RelativeBaseAdjust,
+    ReturnFromInterrupt,
     Finish
 }
 
-impl TryFrom<i64> for InstructionCode {
+impl TryFrom<Word> for InstructionCode {
     type Error = String;
 
-    fn try_from(param: i64) -> Result<Self, Self::Error> {
+    fn try_from(param: Word) -> Result<Self, Self::Error> {
         match param {
             1 => Ok(InstructionCode::Addition),
             2 => Ok(InstructionCode::Multiplication),
@@ -46,12 +88,26 @@ impl TryFrom<i64> for InstructionCode {
             7 => Ok(InstructionCode::LessThan),
             8 => Ok(InstructionCode::Equals),
             9 => Ok(InstructionCode::RelativeBaseAdjust),
+            RETURN_FROM_INTERRUPT => Ok(InstructionCode::ReturnFromInterrupt),
             99 => Ok(InstructionCode::Finish),
             _ => Err(String::from("Invalid op code"))
         }
     }
 }
 
+impl InstructionCode {
+    pub fn length(&self) -> usize {
+        match self {
+            InstructionCode::Addition | InstructionCode::Multiplication => 4,
+            InstructionCode::LessThan | InstructionCode::Equals => 4,
+            InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse => 3,
+            InstructionCode::Input | InstructionCode::Output => 2,
+            InstructionCode::RelativeBaseAdjust => 2,
+            InstructionCode::ReturnFromInterrupt | InstructionCode::Finish => 1
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ParameterMode {
     Position,
@@ -64,8 +120,8 @@ pub struct ParameterExtension {
     modes: Vec<ParameterMode>
 }
 
-impl From<i64> for ParameterExtension {
-    fn from(params: i64) -> Self {
+impl From<Word> for ParameterExtension {
+    fn from(params: Word) -> Self {
         let mut modes: Vec<ParameterMode> = Vec::new();
         let mut n = params;
 
@@ -79,7 +135,7 @@ impl From<i64> for ParameterExtension {
 
             modes.push(current_mode);
 
-            n = n / 10;
+            n /= 10;
         }
 
         ParameterExtension { modes }
@@ -95,192 +151,473 @@ impl ParameterExtension {
     }
 }
 
-pub struct Program {
-    memory: Vec<i64>,
+// A single memory word. Everything a program reads, writes, takes in or puts out is one,
+// so hosts use this name instead of repeating the width.
+pub type Word = i64;
+
+// Anything that can lend out a word slice can back a program, from the usual growable
+// `Vec` to a fixed buffer handed in by the caller
+pub trait Memory: AsRef<[Word]> + AsMut<[Word]> {}
+
+impl<M: AsRef<[Word]> + AsMut<[Word]>> Memory for M {}
+
+pub struct Program<M = Vec<Word>> {
+    memory: M,
     pc: usize,
-    inputs: Vec<i64>,
-    relative_base: i64
+    inputs: Vec<Word>,
+    relative_base: Word,
+    steps: u64,
+    image_len: usize,
+    devices: Vec<(Range<usize>, Box<dyn Device>)>,
+    interrupts: Option<Interrupts>
 }
 
-impl From<String> for Program {
-    fn from(program_string: String) -> Self {
-        let mut memory: Vec<i64> = program_string.split(",").map(|s| s.parse::<i64>().unwrap()).collect();
-        for _ in 0..2000 { memory.push(0); }
+// Zeroed words after the image, for programs that use memory past their own end
+pub(crate) const SCRATCH: usize = 2000;
+
+impl From<Vec<Word>> for Program {
+    fn from(mut memory: Vec<Word>) -> Self {
+        let image_len = memory.len();
+        memory.resize(image_len + SCRATCH, 0);
 
         Program {
             memory,
             pc: 0,
             inputs: Vec::new(),
-            relative_base: 0
+            relative_base: 0,
+            steps: 0,
+            image_len,
+            devices: Vec::new(),
+            interrupts: None
+        }
+    }
+}
+
+impl From<String> for Program {
+    fn from(program_string: String) -> Self {
+        match program_string.parse() {
+            Ok(program) => program,
+            Err(e) => panic!("{}", e)
         }
     }
 }
 
+impl FromStr for Program {
+    type Err = ParseError;
+
+    fn from_str(program_string: &str) -> Result<Self, Self::Err> {
+        parse::parse(program_string).map(Program::from)
+    }
+}
+
+impl TryFrom<&str> for Program {
+    type Error = ParseError;
+
+    fn try_from(program_string: &str) -> Result<Self, Self::Error> {
+        program_string.parse()
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum ProgramResult {
-    Output(i64),
+    Output(Word),
     Complete
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ProgramError {
+    NoInput { pc: usize },
+    ImageTooLarge { len: usize, capacity: usize },
+    InvalidOpcode { pc: usize, value: Word },
+    AddressOutOfRange { pc: usize, address: Word },
+    Overflow { pc: usize }
+}
+
+impl core::fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ProgramError::NoInput { pc } => write!(f, "no input available for instruction at {}", pc),
+            ProgramError::ImageTooLarge { len, capacity } => write!(f, "image of {} words does not fit in {} words of memory", len, capacity),
+            ProgramError::InvalidOpcode { pc, value } => write!(f, "invalid opcode {} at {}", value, pc),
+            ProgramError::AddressOutOfRange { pc, address } => write!(f, "address {} is out of range for instruction at {}", address, pc),
+            ProgramError::Overflow { pc } => write!(f, "arithmetic overflow in instruction at {}", pc)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProgramError {}
+
+#[cfg(feature = "std")]
 impl Program {
-    pub fn run(&mut self, inputs: &mut Vec<i64>) -> Vec<i64> {
-        let mut codes: Vec<i64> = Vec::new();
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, ParseError> {
+        parse::parse_reader(reader).map(Program::from)
+    }
+}
 
-        self.append_inputs(inputs);
+impl<M: Memory> Program<M> {
+    pub fn with_memory(mut memory: M, image: &[Word]) -> Result<Self, ProgramError> {
+        let buffer = memory.as_mut();
+        if image.len() > buffer.len() {
+            return Err(ProgramError::ImageTooLarge { len: image.len(), capacity: buffer.len() });
+        }
 
-        loop {
-            let code = match self.run_until_event() {
-                ProgramResult::Output(result) => result,
-                ProgramResult::Complete => break
-            };
+        buffer[..image.len()].copy_from_slice(image);
+        buffer[image.len()..].iter_mut().for_each(|word| *word = 0);
 
+        Ok(Program {
+            memory,
+            pc: 0,
+            inputs: Vec::new(),
+            relative_base: 0,
+            steps: 0,
+            image_len: image.len(),
+            devices: Vec::new(),
+            interrupts: None
+        })
+    }
+
+    pub fn run(&mut self, inputs: &mut Vec<Word>) -> Vec<Word> {
+        self.run_with(inputs, &mut ())
+    }
+
+    pub fn run_with<O: Observer<M>>(&mut self, inputs: &mut Vec<Word>, observer: &mut O) -> Vec<Word> {
+        let mut codes: Vec<Word> = Vec::new();
+
+        self.append_inputs(inputs);
+
+        while let ProgramResult::Output(code) = self.run_until_event_with(observer) {
             codes.push(code);
         }
 
         codes
     }
 
-    pub fn append_inputs(&mut self, inputs: &mut Vec<i64>) {
+    pub fn outputs<F: FnMut() -> Option<Word>>(&mut self, input: F) -> Outputs<'_, F, M> {
+        Outputs::new(self, input)
+    }
+
+    pub fn append_inputs(&mut self, inputs: &mut Vec<Word>) {
         self.inputs.append(inputs);
     }
 
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &[Word] {
+        self.memory.as_ref()
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [Word] {
+        self.memory.as_mut()
+    }
+
+    pub fn image(&self) -> &[Word] {
+        &self.memory.as_ref()[..self.image_len]
+    }
+
+    // A device may be mapped on fewer addresses than it has, never more, and never on top of
+    // another device
+    pub fn map_device(&mut self, addresses: Range<usize>, device: Box<dyn Device>) -> Result<(), String> {
+        if let Some(capacity) = device.capacity() {
+            if addresses.len() > capacity {
+                return Err(format!("{:?} is {} addresses, but the device only has {}", addresses, addresses.len(), capacity));
+            }
+        }
+
+        if let Some((range, _)) = self.devices.iter().find(|(range, _)| range.start < addresses.end && addresses.start < range.end) {
+            return Err(format!("{:?} overlaps the device already mapped on {:?}", addresses, range));
+        }
+
+        self.devices.push((addresses, device));
+        Ok(())
+    }
+
     pub fn run_until_event(&mut self) -> ProgramResult {
+        self.run_until_event_with(&mut ())
+    }
+
+    pub fn run_until_event_with<O: Observer<M>>(&mut self, observer: &mut O) -> ProgramResult {
         loop {
-            let instruction = Instruction::from(*self.memory.get(self.pc).unwrap());
-            let mut result: Option<ProgramResult> = None;
+            if let Some(event) = self.step_with(observer) {
+                return event;
+            }
+        }
+    }
 
-            self.pc = match instruction.code {
-                InstructionCode::Addition => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
-                    let destination = self.destination_for(self.pc, 3, &instruction.modes);
+    pub(crate) fn next_code(&self) -> Option<InstructionCode> {
+        let value = *self.memory.as_ref().get(self.pc)?;
+        InstructionCode::try_from(value % 100).ok()
+    }
 
+    pub fn awaiting_input(&self) -> bool {
+        self.inputs.is_empty() && self.next_code() == Some(InstructionCode::Input)
+    }
 
-                    self.store_result(p1 + p2, destination);
+    pub fn step(&mut self) -> Option<ProgramResult> {
+        self.step_with(&mut ())
+    }
 
-                    self.pc + 4
-                },
+    pub fn step_with<O: Observer<M>>(&mut self, observer: &mut O) -> Option<ProgramResult> {
+        match self.try_step_with(observer) {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error)
+        }
+    }
 
-                InstructionCode::Multiplication => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
-                    let destination = self.destination_for(self.pc, 3, &instruction.modes);
+    pub fn try_step(&mut self) -> Result<Option<ProgramResult>, ProgramError> {
+        self.try_step_with(&mut ())
+    }
 
-                    self.store_result(p1 * p2, destination);
+    // A failed step leaves the pc and step count where they were, with one exception: an
+    // interrupt taken at the start of the step stays taken, so the pc is then the handler's
+    // first instruction. Devices may still have seen reads made before the failing operand.
+    pub fn try_step_with<O: Observer<M>>(&mut self, observer: &mut O) -> Result<Option<ProgramResult>, ProgramError> {
+        self.dispatch_interrupt(observer)?;
+
+        let pc = self.pc;
+        let value = *self.memory.as_ref().get(pc).ok_or(ProgramError::AddressOutOfRange { pc, address: pc as Word })?;
+        let code = InstructionCode::try_from(value % 100)
+            .ok()
+            .filter(|code| *code != InstructionCode::ReturnFromInterrupt || self.in_interrupt())
+            .ok_or(ProgramError::InvalidOpcode { pc, value })?;
+        let instruction = Instruction { code, modes: ParameterExtension::from(value / 100) };
+
+        if code == InstructionCode::Input && self.inputs.is_empty() {
+            return Err(ProgramError::NoInput { pc });
+        }
 
-                    self.pc + 4
-                },
+        self.steps += 1;
 
-                InstructionCode::Input => {
-                    let next_input = self.inputs.remove(0);
-                    let destination = self.destination_for(self.pc, 1, &instruction.modes);
+        observer.before_instruction(self, code);
 
-                    self.store_result(next_input, destination);
+        let (next, result) = match self.execute(observer, &instruction) {
+            Ok(executed) => executed,
+            Err(error) => {
+                self.steps -= 1;
+                return Err(error);
+            }
+        };
 
-                    self.pc + 2
-                },
+        self.pc = next;
 
-                InstructionCode::Output => {
-                    let output = self.parameter_for(self.pc, 1, &instruction.modes);
-                    result = Some(ProgramResult::Output(output));
+        for (_, device) in &mut self.devices {
+            device.tick();
+        }
+        self.tick_timer();
 
-                    self.pc + 2
-                },
+        observer.after_instruction(self, pc, code);
 
-                InstructionCode::JumpIfTrue => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
+        Ok(result)
+    }
 
-                    match p1 {
-                        0 => self.pc + 3,
-                        _ => p2 as usize
-                    }
-                }
+    pub fn try_run_until_event(&mut self) -> Result<ProgramResult, ProgramError> {
+        loop {
+            if let Some(event) = self.try_step()? {
+                return Ok(event);
+            }
+        }
+    }
 
-                InstructionCode::JumpIfFalse => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
+    fn execute<O: Observer<M>>(&mut self, observer: &mut O, instruction: &Instruction) -> Result<(usize, Option<ProgramResult>), ProgramError> {
+        let pc = self.pc;
+        let modes = &instruction.modes;
+        let overflow = ProgramError::Overflow { pc };
 
-                    match p1 {
-                        0 => p2 as usize,
-                        _ => self.pc + 3
-                    }
-                }
+        let next = match instruction.code {
+            InstructionCode::Addition => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+                let destination = self.destination_for(pc, 3, modes)?;
 
-                InstructionCode::LessThan => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
-                    let destination = self.destination_for(self.pc, 3, &instruction.modes);
-                    
-                    let result = if p1 < p2 { 1 } else { 0 }; 
-                    self.store_result(result, destination);
+                self.store_result(observer, p1.checked_add(p2).ok_or(overflow)?, destination);
 
-                    self.pc + 4
-                }
+                pc + 4
+            },
 
-                InstructionCode::Equals => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    let p2 = self.parameter_for(self.pc, 2, &instruction.modes);
-                    let destination = self.destination_for(self.pc, 3, &instruction.modes);
-                    
-                    let result = if p1 == p2 { 1 } else { 0 }; 
-                    self.store_result(result, destination);
+            InstructionCode::Multiplication => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+                let destination = self.destination_for(pc, 3, modes)?;
 
-                    self.pc + 4
-                }
+                self.store_result(observer, p1.checked_mul(p2).ok_or(overflow)?, destination);
 
-                InstructionCode::RelativeBaseAdjust => {
-                    let p1 = self.parameter_for(self.pc, 1, &instruction.modes);
-                    self.relative_base = self.relative_base + p1;
+                pc + 4
+            },
 
-                    self.pc + 2
-                }
+            InstructionCode::Input => {
+                let destination = self.destination_for(pc, 1, modes)?;
+                let next_input = self.inputs.remove(0);
+                observer.on_input(next_input);
 
-                InstructionCode::Finish => {
-                    result = Some(ProgramResult::Complete);
+                self.store_result(observer, next_input, destination);
 
-                    self.pc
-                }
-            };
+                pc + 2
+            },
 
-            if let Some(event) = result {
-                return event;
+            InstructionCode::Output => {
+                let output = self.parameter_for(observer, pc, 1, modes)?;
+                observer.on_output(output);
+
+                return Ok((pc + 2, Some(ProgramResult::Output(output))));
+            },
+
+            InstructionCode::JumpIfTrue => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+
+                let taken = p1 != 0;
+                let next = if taken { jump_target(pc, p2)? } else { pc + 3 };
+                observer.on_branch(pc, taken);
+
+                next
             }
-        }
+
+            InstructionCode::JumpIfFalse => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+
+                let taken = p1 == 0;
+                let next = if taken { jump_target(pc, p2)? } else { pc + 3 };
+                observer.on_branch(pc, taken);
+
+                next
+            }
+
+            InstructionCode::LessThan => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+                let destination = self.destination_for(pc, 3, modes)?;
+
+                let result = if p1 < p2 { 1 } else { 0 };
+                self.store_result(observer, result, destination);
+
+                pc + 4
+            }
+
+            InstructionCode::Equals => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let p2 = self.parameter_for(observer, pc, 2, modes)?;
+                let destination = self.destination_for(pc, 3, modes)?;
+
+                let result = if p1 == p2 { 1 } else { 0 };
+                self.store_result(observer, result, destination);
+
+                pc + 4
+            }
+
+            InstructionCode::RelativeBaseAdjust => {
+                let p1 = self.parameter_for(observer, pc, 1, modes)?;
+                let base = self.relative_base.checked_add(p1).ok_or(overflow)?;
+                observer.on_relative_base(self.relative_base, base);
+                self.relative_base = base;
+
+                pc + 2
+            }
+
+            InstructionCode::ReturnFromInterrupt => self.return_from_interrupt(observer),
+
+            InstructionCode::Finish => return Ok((pc, Some(ProgramResult::Complete)))
+        };
+
+        Ok((next, None))
+    }
+
+    fn operand(&self, pc: usize, index: usize) -> Result<Word, ProgramError> {
+        self.memory.as_ref().get(pc + index).copied()
+            .ok_or(ProgramError::AddressOutOfRange { pc, address: (pc + index) as Word })
     }
 
-    fn parameter_for(&self, pc: usize, index: usize, ext: &ParameterExtension) -> i64 {
+    // Addresses are valid if they land in memory or on a mapped device
+    fn address(&self, pc: usize, address: Word) -> Result<usize, ProgramError> {
+        usize::try_from(address).ok()
+            .filter(|a| *a < self.memory.as_ref().len() || self.devices.iter().any(|(range, _)| range.contains(a)))
+            .ok_or(ProgramError::AddressOutOfRange { pc, address })
+    }
+
+    fn parameter_for<O: Observer<M>>(&mut self, observer: &mut O, pc: usize, index: usize, ext: &ParameterExtension) -> Result<Word, ProgramError> {
         let mode = ext.at_position(index - 1);
-        let value = self.memory[pc + index];
+        let value = self.operand(pc, index)?;
 
-        match mode {
-            ParameterMode::Position => self.memory[value as usize],
-            ParameterMode::Relative => self.memory[(value + self.relative_base) as usize],
-            _ => value
-        }
+        let address = match mode {
+            ParameterMode::Position => self.address(pc, value)?,
+            ParameterMode::Relative => {
+                let address = value.checked_add(self.relative_base).ok_or(ProgramError::Overflow { pc })?;
+                self.address(pc, address)?
+            },
+            _ => return Ok(value)
+        };
+
+        let value = match self.device_at(address) {
+            Some((range, device)) => device.read(address - range.start),
+            None => self.memory.as_ref()[address]
+        };
+
+        observer.on_read(address, value);
+
+        Ok(value)
     }
 
-    fn destination_for(&self, pc: usize, index: usize, ext: &ParameterExtension) -> i64 {
+    fn destination_for(&self, pc: usize, index: usize, ext: &ParameterExtension) -> Result<usize, ProgramError> {
         let mode = ext.at_position(index - 1);
-        let value = self.memory[pc + index];
+        let value = self.operand(pc, index)?;
 
         match mode {
-            ParameterMode::Relative => value + self.relative_base,
-            _ => value
+            ParameterMode::Relative => {
+                let address = value.checked_add(self.relative_base).ok_or(ProgramError::Overflow { pc })?;
+                self.address(pc, address)
+            },
+            _ => self.address(pc, value)
+        }
+    }
+
+    fn store_result<O: Observer<M>>(&mut self, observer: &mut O, result: Word, position: usize) {
+        observer.on_write(position, result);
+
+        if let Some((range, device)) = self.device_at(position) {
+            device.write(position - range.start, result);
+            return;
         }
+
+        self.memory.as_mut()[position] = result;
     }
 
-    fn store_result(&mut self, result: i64, position: i64) {
-        self.memory[position as usize] = result;
+    fn device_at(&mut self, address: usize) -> Option<(Range<usize>, &mut Box<dyn Device>)> {
+        self.devices.iter_mut()
+            .find(|(range, _)| range.contains(&address))
+            .map(|(range, device)| (range.clone(), device))
     }
 }
 
-#[test]
-fn test_program() {
-    use std::fs;
-
-    let input = fs::read_to_string("test.txt").unwrap();
-    let mut program = Program::from(input);
-    let diagnostic_codes = program.run(&mut vec!(8));
+fn jump_target(pc: usize, target: Word) -> Result<usize, ProgramError> {
+    usize::try_from(target).map_err(|_| ProgramError::AddressOutOfRange { pc, address: target })
+}
 
-    assert_eq!(*diagnostic_codes.last().unwrap(), 1000);
+#[test]
+fn test_try_step_errors() {
+    let mut program = Program::from(vec!(1101, 1, 1, 0, 42));
+    assert_eq!(program.try_step(), Ok(None));
+    assert_eq!(program.try_step(), Err(ProgramError::InvalidOpcode { pc: 4, value: 42 }));
+    assert_eq!((program.pc(), program.steps()), (4, 1));
+
+    let mut program = Program::from(vec!(109, -5, 204, 0, 99));
+    assert_eq!(program.try_step(), Ok(None));
+    assert_eq!(program.try_step(), Err(ProgramError::AddressOutOfRange { pc: 2, address: -5 }));
+
+    let mut program = Program::from(vec!(1102, Word::MAX, 2, 0, 99));
+    assert_eq!(program.try_step(), Err(ProgramError::Overflow { pc: 0 }));
+    assert_eq!(program.memory()[0], 1102);
+
+    let mut program = Program::from(vec!(3, 0, 99));
+    assert_eq!(program.try_step(), Err(ProgramError::NoInput { pc: 0 }));
+    assert_eq!(program.steps(), 0);
 }
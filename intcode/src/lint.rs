@@ -0,0 +1,151 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use crate::{InstructionCode, Word};
+
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    UndecodableOpcode { address: usize, value: Word },
+    ImmediateWrite { address: usize, parameter: usize },
+    InvalidMode { address: usize, parameter: usize, digit: Word },
+    JumpOutsideImage { address: usize, target: Word },
+    ReturnOutsideHandler { address: usize }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::UndecodableOpcode { address, value } =>
+                write!(f, "{}: undecodable opcode {}", address, value),
+            Issue::ImmediateWrite { address, parameter } =>
+                write!(f, "{}: parameter {} is written to but uses immediate mode", address, parameter),
+            Issue::InvalidMode { address, parameter, digit } =>
+                write!(f, "{}: parameter {} has unknown mode {}", address, parameter, digit),
+            Issue::JumpOutsideImage { address, target } =>
+                write!(f, "{}: jump to {} is outside the image", address, target),
+            Issue::ReturnOutsideHandler { address } =>
+                write!(f, "{}: return from interrupt outside a handler", address)
+        }
+    }
+}
+
+fn mode_digit(value: Word, parameter: usize) -> Word {
+    (value / Word::pow(10, parameter as u32 + 1)) % 10
+}
+
+fn writes_parameter(code: InstructionCode) -> Option<usize> {
+    match code {
+        InstructionCode::Addition | InstructionCode::Multiplication => Some(3),
+        InstructionCode::LessThan | InstructionCode::Equals => Some(3),
+        InstructionCode::Input => Some(1),
+        _ => None
+    }
+}
+
+// Follows fall-through and constant jump targets from each root. Reachable words that do not
+// decode are kept as `None` so callers can tell code from garbage.
+pub(crate) fn walk(image: &[Word], roots: Vec<usize>) -> BTreeMap<usize, Option<InstructionCode>> {
+    let mut visited: BTreeMap<usize, Option<InstructionCode>> = BTreeMap::new();
+    let mut pending = roots;
+
+    while let Some(address) = pending.pop() {
+        if address >= image.len() || visited.contains_key(&address) { continue; }
+
+        let value = image[address];
+        let code = if value < 0 { None } else { InstructionCode::try_from(value % 100).ok() };
+        visited.insert(address, code);
+
+        let code = match code {
+            Some(code) => code,
+            None => continue
+        };
+
+        let mut falls_through = !matches!(code, InstructionCode::Finish | InstructionCode::ReturnFromInterrupt);
+
+        if let InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse = code {
+            let condition = image.get(address + 1).copied();
+            let target = image.get(address + 2).copied();
+
+            if let (1, Some(target)) = (mode_digit(value, 2), target) {
+                if target >= 0 { pending.push(target as usize); }
+            }
+
+            // 1105,1,x and 1106,0,x are plain gotos
+            if let (1, Some(condition)) = (mode_digit(value, 1), condition) {
+                falls_through = (condition == 0) == (code == InstructionCode::JumpIfTrue);
+            }
+        }
+
+        if falls_through {
+            pending.push(address + code.length());
+        }
+    }
+
+    visited
+}
+
+pub fn lint(image: &[Word]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (address, code) in walk(image, vec!(0)) {
+        let value = image[address];
+        let code = match code {
+            Some(code) => code,
+            None => {
+                issues.push(Issue::UndecodableOpcode { address, value });
+                continue;
+            }
+        };
+
+        for parameter in 1..code.length() {
+            let digit = mode_digit(value, parameter);
+            if digit > 2 {
+                issues.push(Issue::InvalidMode { address, parameter, digit });
+            }
+        }
+
+        // Handlers are only entered through interrupts, so nothing reached from the entry
+        // point is running one
+        if code == InstructionCode::ReturnFromInterrupt {
+            issues.push(Issue::ReturnOutsideHandler { address });
+        }
+
+        if let Some(parameter) = writes_parameter(code) {
+            if mode_digit(value, parameter) == 1 {
+                issues.push(Issue::ImmediateWrite { address, parameter });
+            }
+        }
+
+        if let InstructionCode::JumpIfTrue | InstructionCode::JumpIfFalse = code {
+            if let (1, Some(&target)) = (mode_digit(value, 2), image.get(address + 2)) {
+                if target < 0 || target as usize >= image.len() {
+                    issues.push(Issue::JumpOutsideImage { address, target });
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+#[test]
+fn test_lint_clean_program() {
+    assert_eq!(lint(&[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9]), vec!());
+}
+
+#[test]
+fn test_lint_issues() {
+    let image = [11101, 1, 2, 3, 304, 5, 1105, 1, 40, 42];
+
+    assert_eq!(lint(&image), vec!(
+        Issue::ImmediateWrite { address: 0, parameter: 3 },
+        Issue::InvalidMode { address: 4, parameter: 1, digit: 3 },
+        Issue::JumpOutsideImage { address: 6, target: 40 }
+    ));
+
+    assert_eq!(lint(&[1105, 1, 3, 42]), vec!(Issue::UndecodableOpcode { address: 3, value: 42 }));
+    assert_eq!(lint(&[98]), vec!(Issue::ReturnOutsideHandler { address: 0 }));
+    assert_eq!(lint(&[1105, 1, 0, 98]), vec!());
+}
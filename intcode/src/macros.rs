@@ -0,0 +1,268 @@
+// Support for `intcode!`. Everything here runs in const context, so a bad program is a
+// compile error at the macro call rather than a panic when it runs.
+
+use crate::Word;
+use crate::interrupt::RETURN_FROM_INTERRUPT;
+
+const DATA: Word = 0;
+
+pub struct Instruction {
+    pub code: Word,
+    pub operands: &'static [(Word, Word)]
+}
+
+const fn same(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() { return false; }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] { return false; }
+        i += 1;
+    }
+
+    true
+}
+
+pub const fn opcode(mnemonic: &str) -> Word {
+    const NAMES: [(&str, Word); 12] = [
+        ("add", 1), ("mul", 2), ("in", 3), ("out", 4), ("jt", 5), ("jf", 6),
+        ("lt", 7), ("eq", 8), ("arb", 9), ("rfi", RETURN_FROM_INTERRUPT), ("hlt", 99), ("data", DATA)
+    ];
+
+    let mut i = 0;
+    while i < NAMES.len() {
+        if same(NAMES[i].0, mnemonic) { return NAMES[i].1; }
+        i += 1;
+    }
+
+    panic!("intcode!: unknown mnemonic")
+}
+
+// Instruction length for a valid opcode, zero otherwise
+const fn length(code: Word) -> usize {
+    match code {
+        1 | 2 | 7 | 8 => 4,
+        5 | 6 => 3,
+        3 | 4 | 9 => 2,
+        RETURN_FROM_INTERRUPT | 99 => 1,
+        _ => 0
+    }
+}
+
+const fn mode(value: Word, parameter: usize) -> Word {
+    (value / Word::pow(10, parameter as u32 + 1)) % 10
+}
+
+const fn writes(code: Word, parameter: usize) -> bool {
+    matches!((code, parameter), (1 | 2 | 7 | 8, 3) | (3, 1))
+}
+
+pub const fn size(program: &[Instruction]) -> usize {
+    let mut total = 0;
+    let mut i = 0;
+
+    while i < program.len() {
+        let operands = program[i].operands.len();
+        total += if program[i].code == DATA { operands } else { operands + 1 };
+        i += 1;
+    }
+
+    total
+}
+
+pub const fn assemble<const N: usize>(program: &[Instruction]) -> [Word; N] {
+    let mut words = [0; N];
+    let mut at = 0;
+    let mut i = 0;
+
+    while i < program.len() {
+        let Instruction { code, operands } = program[i];
+
+        if code != DATA {
+            if operands.len() + 1 != length(code) { panic!("intcode!: wrong number of operands"); }
+
+            let mut word = code;
+            let mut place = 100;
+            let mut p = 0;
+            while p < operands.len() {
+                word += operands[p].0 * place;
+                place *= 10;
+                p += 1;
+            }
+
+            words[at] = word;
+            at += 1;
+        }
+
+        let mut p = 0;
+        while p < operands.len() {
+            if code == DATA && operands[p].0 != 1 { panic!("intcode!: data takes plain numbers"); }
+
+            words[at] = operands[p].1;
+            at += 1;
+            p += 1;
+        }
+
+        i += 1;
+    }
+
+    words
+}
+
+// The same reachability rules as `lint`: follow fall-through and immediate jump targets from
+// address 0, and reject anything reachable that the VM could not run
+pub const fn checked<const N: usize>(words: [Word; N]) -> [Word; N] {
+    let mut queued = [false; N];
+    let mut pending = [0; N];
+    let mut count = 0;
+
+    if N > 0 {
+        queued[0] = true;
+        pending[0] = 0;
+        count = 1;
+    }
+
+    while count > 0 {
+        count -= 1;
+        let address = pending[count];
+        let value = words[address];
+        let code = if value < 0 { 0 } else { value % 100 };
+        let len = length(code);
+
+        if len == 0 { panic!("intcode!: undecodable opcode"); }
+        if address + len > N { panic!("intcode!: instruction runs past the end of the program"); }
+        if code == RETURN_FROM_INTERRUPT { panic!("intcode!: rfi reachable outside an interrupt handler"); }
+
+        let mut parameter = 1;
+        while parameter < len {
+            let digit = mode(value, parameter);
+            if digit > 2 { panic!("intcode!: invalid parameter mode"); }
+            if digit == 1 && writes(code, parameter) { panic!("intcode!: written parameter uses immediate mode"); }
+            parameter += 1;
+        }
+
+        let mut falls_through = code != 99;
+
+        if code == 5 || code == 6 {
+            if mode(value, 2) == 1 {
+                let target = words[address + 2];
+                if target < 0 || target as usize >= N { panic!("intcode!: jump outside the program"); }
+
+                if !queued[target as usize] {
+                    queued[target as usize] = true;
+                    pending[count] = target as usize;
+                    count += 1;
+                }
+            }
+
+            // 1105,1,x and 1106,0,x are plain gotos
+            if mode(value, 1) == 1 {
+                falls_through = (words[address + 1] == 0) == (code == 5);
+            }
+        }
+
+        let next = address + len;
+        if falls_through && next < N && !queued[next] {
+            queued[next] = true;
+            pending[count] = next;
+            count += 1;
+        }
+    }
+
+    words
+}
+
+/// Takes either raw words, `intcode![3, 0, 4, 0, 99]`, or mnemonics with one call per
+/// instruction, `intcode! { in([0]) out([0]) hlt() }`. Operands are immediate `5`, position
+/// `[5]` or relative `[rb + 5]`, and `data(...)` places plain words.
+///
+/// A program the VM could not run does not compile. An opcode reachable from address 0:
+///
+/// ```compile_fail,E0080
+/// let program = intcode::intcode![1101, 1, 2, 3, 42];
+/// ```
+///
+/// A write to an immediate operand:
+///
+/// ```compile_fail,E0080
+/// let program = intcode::intcode! { add(1, 2, 3) hlt() };
+/// ```
+///
+/// A return from interrupt the program reaches on its own, outside any handler:
+///
+/// ```compile_fail,E0080
+/// let program = intcode::intcode! { out(1) rfi() };
+/// ```
+///
+/// The same programs with the mistakes fixed:
+///
+/// ```
+/// let mut program = intcode::intcode![1101, 1, 2, 3, 99];
+/// assert_eq!(program.run(&mut vec!()), vec!());
+///
+/// let program = intcode::intcode! { add(1, 2, [3]) hlt() };
+/// assert_eq!(program.image(), &[1101, 1, 2, 3, 99][..]);
+///
+/// let program = intcode::intcode! { out(1) hlt() rfi() };
+/// assert_eq!(program.image(), &[104, 1, 99, 98][..]);
+/// ```
+#[macro_export]
+macro_rules! intcode {
+    (@operands [$($done:expr),*]) => { [$($done),*] };
+    (@operands [$($done:expr),*] [rb] $(, $($rest:tt)*)?) => {
+        $crate::intcode!(@operands [$($done,)* (2, 0)] $($($rest)*)?)
+    };
+    (@operands [$($done:expr),*] [rb + $n:literal] $(, $($rest:tt)*)?) => {
+        $crate::intcode!(@operands [$($done,)* (2, $n)] $($($rest)*)?)
+    };
+    (@operands [$($done:expr),*] [rb - $n:literal] $(, $($rest:tt)*)?) => {
+        $crate::intcode!(@operands [$($done,)* (2, -$n)] $($($rest)*)?)
+    };
+    (@operands [$($done:expr),*] [$n:literal] $(, $($rest:tt)*)?) => {
+        $crate::intcode!(@operands [$($done,)* (0, $n)] $($($rest)*)?)
+    };
+    (@operands [$($done:expr),*] $n:literal $(, $($rest:tt)*)?) => {
+        $crate::intcode!(@operands [$($done,)* (1, $n)] $($($rest)*)?)
+    };
+    ($($word:literal),* $(,)?) => {{
+        const WORDS: &[$crate::Word] = &$crate::macros::checked([$($word),*]);
+        $crate::Program::from(WORDS.to_vec())
+    }};
+    ($($mnemonic:ident ( $($operands:tt)* ) $(;)?)*) => {{
+        const PROGRAM: &[$crate::macros::Instruction] = &[$(
+            $crate::macros::Instruction {
+                code: $crate::macros::opcode(stringify!($mnemonic)),
+                operands: &$crate::intcode!(@operands [] $($operands)*)
+            }
+        ),*];
+        const WORDS: [$crate::Word; $crate::macros::size(PROGRAM)] = $crate::macros::checked($crate::macros::assemble(PROGRAM));
+        $crate::Program::from(WORDS.to_vec())
+    }};
+}
+
+#[test]
+fn test_raw_words() {
+    let mut program = intcode![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+    assert_eq!(program.run(&mut vec!()), vec!(109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99));
+}
+
+#[test]
+fn test_mnemonics() {
+    let program = intcode! {
+        in([9])
+        eq([9], 8, [rb + 10])
+        out([rb + 10])
+        hlt()
+        data(-1)
+    };
+
+    assert_eq!(program.image(), &[3, 9, 21008, 9, 8, 10, 204, 10, 99, -1][..]);
+    assert_eq!(crate::Program::from(program.image().to_vec()).run(&mut vec!(8)), vec!(1));
+}
+
+#[test]
+#[should_panic(expected = "written parameter uses immediate mode")]
+fn test_checked_rejects_immediate_write() {
+    checked([11101, 1, 2, 3, 99]);
+}
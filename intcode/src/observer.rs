@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+use crate::{InstructionCode, Program, Word};
+
+// Every hook defaults to doing nothing. The VM is generic over its observer, so running with
+// `()` compiles the hooks away entirely.
+pub trait Observer<M = Vec<Word>> {
+    fn before_instruction(&mut self, _program: &Program<M>, _code: InstructionCode) {}
+    fn after_instruction(&mut self, _program: &Program<M>, _pc: usize, _code: InstructionCode) {}
+    fn on_read(&mut self, _address: usize, _value: Word) {}
+    fn on_write(&mut self, _address: usize, _value: Word) {}
+    fn on_relative_base(&mut self, _old: Word, _new: Word) {}
+    fn on_branch(&mut self, _pc: usize, _taken: bool) {}
+    fn on_input(&mut self, _value: Word) {}
+    fn on_output(&mut self, _value: Word) {}
+    fn on_interrupt(&mut self, _line: Word, _pc: usize) {}
+}
+
+impl<M> Observer<M> for () {}
+
+impl<M, A: Observer<M>, B: Observer<M>> Observer<M> for (A, B) {
+    fn before_instruction(&mut self, program: &Program<M>, code: InstructionCode) {
+        self.0.before_instruction(program, code);
+        self.1.before_instruction(program, code);
+    }
+
+    fn after_instruction(&mut self, program: &Program<M>, pc: usize, code: InstructionCode) {
+        self.0.after_instruction(program, pc, code);
+        self.1.after_instruction(program, pc, code);
+    }
+
+    fn on_read(&mut self, address: usize, value: Word) {
+        self.0.on_read(address, value);
+        self.1.on_read(address, value);
+    }
+
+    fn on_write(&mut self, address: usize, value: Word) {
+        self.0.on_write(address, value);
+        self.1.on_write(address, value);
+    }
+
+    fn on_relative_base(&mut self, old: Word, new: Word) {
+        self.0.on_relative_base(old, new);
+        self.1.on_relative_base(old, new);
+    }
+
+    fn on_branch(&mut self, pc: usize, taken: bool) {
+        self.0.on_branch(pc, taken);
+        self.1.on_branch(pc, taken);
+    }
+
+    fn on_input(&mut self, value: Word) {
+        self.0.on_input(value);
+        self.1.on_input(value);
+    }
+
+    fn on_output(&mut self, value: Word) {
+        self.0.on_output(value);
+        self.1.on_output(value);
+    }
+
+    fn on_interrupt(&mut self, line: Word, pc: usize) {
+        self.0.on_interrupt(line, pc);
+        self.1.on_interrupt(line, pc);
+    }
+}
+
+#[test]
+fn test_observer() {
+    #[derive(Default)]
+    struct Counter {
+        instructions: usize,
+        writes: Vec<(usize, Word)>,
+        bases: Vec<Word>,
+        outputs: Vec<Word>
+    }
+
+    impl Observer for Counter {
+        fn after_instruction(&mut self, _program: &Program, _pc: usize, _code: InstructionCode) {
+            self.instructions += 1;
+        }
+
+        fn on_write(&mut self, address: usize, value: Word) {
+            self.writes.push((address, value));
+        }
+
+        fn on_relative_base(&mut self, _old: Word, new: Word) {
+            self.bases.push(new);
+        }
+
+        fn on_output(&mut self, value: Word) {
+            self.outputs.push(value);
+        }
+    }
+
+    let mut program = Program::from(String::from("109,5,21101,2,3,0,204,0,99"));
+    let mut counter = Counter::default();
+
+    assert_eq!(program.run_with(&mut vec!(), &mut counter), vec!(5));
+    assert_eq!(counter.instructions, 4);
+    assert_eq!(counter.writes, vec!((5, 5)));
+    assert_eq!(counter.bases, vec!(5));
+    assert_eq!(counter.outputs, vec!(5));
+}
@@ -0,0 +1,72 @@
+use alloc::vec::Vec;
+use crate::{Memory, Program, ProgramError, ProgramResult, Word};
+
+pub struct Outputs<'a, F, M = Vec<Word>> {
+    program: &'a mut Program<M>,
+    input: F,
+    done: bool
+}
+
+impl<'a, F: FnMut() -> Option<Word>, M: Memory> Outputs<'a, F, M> {
+    pub fn new(program: &'a mut Program<M>, input: F) -> Self {
+        Outputs { program, input, done: false }
+    }
+}
+
+// The input closure is only called when the program executes `Input` with nothing queued,
+// so it can depend on outputs already taken from the iterator
+impl<'a, F: FnMut() -> Option<Word>, M: Memory> Iterator for Outputs<'a, F, M> {
+    type Item = Result<Word, ProgramError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done { return None; }
+
+        loop {
+            if self.program.awaiting_input() {
+                match (self.input)() {
+                    Some(value) => self.program.inputs.push(value),
+                    None => {
+                        self.done = true;
+                        return Some(Err(ProgramError::NoInput { pc: self.program.pc() }));
+                    }
+                }
+            }
+
+            match self.program.try_step() {
+                Ok(Some(ProgramResult::Output(value))) => return Some(Ok(value)),
+                Ok(Some(ProgramResult::Complete)) => {
+                    self.done = true;
+                    return None;
+                },
+                Ok(None) => (),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_outputs() {
+    use std::cell::Cell;
+
+    // Echoes each input plus one until it reads a zero; each input is twice the last output
+    let mut program = Program::from(String::from("3,20,1006,20,14,1001,20,1,20,4,20,1105,1,0,99"));
+    let next = Cell::new(1);
+    let mut outputs = Vec::new();
+
+    for output in program.outputs(|| Some(if next.get() > 20 { 0 } else { next.get() })) {
+        let output = output.unwrap();
+        next.set(output * 2);
+        outputs.push(output);
+    }
+
+    assert_eq!(outputs, vec!(2, 5, 11));
+
+    let mut program = Program::from(String::from("3,0,4,0,3,0,4,0,99"));
+    let mut inputs = vec!(7).into_iter();
+    let outputs: Vec<Result<Word, ProgramError>> = program.outputs(|| inputs.next()).collect();
+    assert_eq!(outputs, vec!(Ok(7), Err(ProgramError::NoInput { pc: 4 })));
+}
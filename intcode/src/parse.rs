@@ -0,0 +1,131 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::Read;
+use crate::Word;
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    InvalidToken { line: usize, column: usize, token: String },
+    EmptyItem { line: usize, column: usize },
+    Io(String)
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidToken { line, column, token } =>
+                write!(f, "{}:{}: '{}' is not an integer", line, column, token),
+            ParseError::EmptyItem { line, column } =>
+                write!(f, "{}:{}: empty item", line, column),
+            ParseError::Io(message) =>
+                write!(f, "{}", message)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+struct Token {
+    text: String,
+    line: usize,
+    column: usize
+}
+
+impl Token {
+    fn parse(&self) -> Result<Word, ParseError> {
+        self.text.parse::<Word>().map_err(|_| ParseError::InvalidToken {
+            line: self.line,
+            column: self.column,
+            token: self.text.clone()
+        })
+    }
+}
+
+// Items are separated by commas and/or whitespace, and `#` comments run to the end of the
+// line. Two commas with nothing between them are an error, but a trailing comma is not.
+pub fn parse(text: &str) -> Result<Vec<Word>, ParseError> {
+    let mut words = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut after_comma = true;
+    let mut in_comment = false;
+
+    for (line_index, line) in text.lines().enumerate() {
+        for (column_index, c) in line.chars().enumerate() {
+            let (line, column) = (line_index + 1, column_index + 1);
+
+            if in_comment { break; }
+
+            match c {
+                '#' | ',' => {
+                    if let Some(token) = current.take() {
+                        words.push(token.parse()?);
+                        after_comma = false;
+                    }
+
+                    if c == '#' {
+                        in_comment = true;
+                    } else if after_comma {
+                        return Err(ParseError::EmptyItem { line, column });
+                    } else {
+                        after_comma = true;
+                    }
+                },
+                c if c.is_whitespace() => {
+                    if let Some(token) = current.take() {
+                        words.push(token.parse()?);
+                        after_comma = false;
+                    }
+                },
+                c => match &mut current {
+                    Some(token) => token.text.push(c),
+                    None => current = Some(Token { text: c.to_string(), line, column })
+                }
+            }
+        }
+
+        if let Some(token) = current.take() {
+            words.push(token.parse()?);
+            after_comma = false;
+        }
+
+        in_comment = false;
+    }
+
+    Ok(words)
+}
+
+#[cfg(feature = "std")]
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<Vec<Word>, ParseError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).map_err(|e| ParseError::Io(e.to_string()))?;
+
+    parse(&text)
+}
+
+#[test]
+fn test_parse() {
+    let text = "# day 5 comparison\n3,9,8,9,  # read and compare\n10,9,4,9,\n99,-1,8\n";
+    assert_eq!(parse(text), Ok(vec!(3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8)));
+    assert_eq!(parse("1,2,3,\n"), Ok(vec!(1, 2, 3)));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(parse("1,2\n3,x4,5"), Err(ParseError::InvalidToken { line: 2, column: 3, token: String::from("x4") }));
+    assert_eq!(parse("1,,2"), Err(ParseError::EmptyItem { line: 1, column: 3 }));
+    assert_eq!(parse(",1"), Err(ParseError::EmptyItem { line: 1, column: 1 }));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_program_from_text() {
+    use std::convert::TryFrom;
+    use crate::Program;
+
+    let mut program = Program::from_reader("104,7,\n99\n".as_bytes()).unwrap();
+    assert_eq!(program.run(&mut vec!()), vec!(7));
+    assert!(Program::try_from("104,7,99,oops").is_err());
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use crate::{InstructionCode, Memory, Program, ProgramError, ProgramResult, Word};
+use crate::observer::Observer;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event {
+    Input(Word),
+    Output(Word)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Record {
+    pub event: Event,
+    pub step: Option<u64>
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Session {
+    records: Vec<Record>
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session { records: Vec::new() }
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn inputs(&self) -> Vec<Word> {
+        self.records.iter().filter_map(|r| match r.event {
+            Event::Input(n) => Some(n),
+            Event::Output(_) => None
+        }).collect()
+    }
+
+    pub fn outputs(&self) -> Vec<Word> {
+        self.records.iter().filter_map(|r| match r.event {
+            Event::Output(n) => Some(n),
+            Event::Input(_) => None
+        }).collect()
+    }
+
+    pub fn push(&mut self, event: Event, step: Option<u64>) {
+        self.records.push(Record { event, step });
+    }
+
+    pub fn load(path: &str) -> Result<Session, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        contents.parse()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| e.to_string())
+    }
+}
+
+// One record per line: `in <value> [step]` or `out <value> [step]`
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# intcode session")?;
+
+        for record in &self.records {
+            match record.event {
+                Event::Input(n) => write!(f, "in {}", n)?,
+                Event::Output(n) => write!(f, "out {}", n)?
+            };
+
+            match record.step {
+                Some(step) => writeln!(f, " {}", step)?,
+                None => writeln!(f)?
+            };
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = String;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let mut session = Session::new();
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let parse = |field: &str| field.parse::<Word>()
+                .map_err(|_| format!("line {}: invalid number '{}'", number + 1, field));
+
+            if fields.len() < 2 || fields.len() > 3 {
+                return Err(format!("line {}: expected '<in|out> <value> [step]'", number + 1));
+            }
+
+            let value = parse(fields[1])?;
+            let event = match fields[0] {
+                "in" => Event::Input(value),
+                "out" => Event::Output(value),
+                other => return Err(format!("line {}: unknown event '{}'", number + 1, other))
+            };
+
+            let step = match fields.get(2) {
+                Some(field) => Some(field.parse::<u64>().map_err(|_| format!("line {}: invalid step '{}'", number + 1, field))?),
+                None => None
+            };
+
+            session.push(event, step);
+        }
+
+        Ok(session)
+    }
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    session: Session,
+    record_steps: bool,
+    step: u64
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder { session: Session::new(), record_steps: false, step: 0 }
+    }
+
+    pub fn with_steps() -> Self {
+        Recorder { session: Session::new(), record_steps: true, step: 0 }
+    }
+
+    fn push(&mut self, event: Event) {
+        let step = if self.record_steps { Some(self.step) } else { None };
+        self.session.push(event, step);
+    }
+
+    pub fn finish(self) -> Session {
+        self.session
+    }
+}
+
+impl<M: Memory> Observer<M> for Recorder {
+    fn before_instruction(&mut self, program: &Program<M>, _code: InstructionCode) {
+        self.step = program.steps();
+    }
+
+    fn on_input(&mut self, value: Word) {
+        self.push(Event::Input(value));
+    }
+
+    fn on_output(&mut self, value: Word) {
+        self.push(Event::Output(value));
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Found {
+    InputRequest,
+    Input(Word),
+    Output(Word),
+    Complete,
+    Error(ProgramError)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub pc: usize,
+    pub steps: u64,
+    pub expected: Option<Record>,
+    pub found: Found
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record {} at pc {} after {} steps: expected {:?}, found {:?}",
+            self.index, self.pc, self.steps, self.expected, self.found)
+    }
+}
+
+pub fn replay(program: &mut Program, session: &Session) -> Result<(), Divergence> {
+    let mut index = 0;
+
+    loop {
+        let expected = session.records.get(index).copied();
+        let diverged = |program: &Program, found: Found| Divergence {
+            index, pc: program.pc(), steps: program.steps(), expected, found
+        };
+
+        // Inputs are fed one at a time so that an unexpected request is caught here
+        // rather than panicking inside the program
+        if program.awaiting_input() {
+            match expected {
+                Some(Record { event: Event::Input(n), .. }) => program.inputs.push(n),
+                _ => return Err(diverged(program, Found::InputRequest))
+            }
+        }
+
+        // Inputs queued before the replay are read too, so the value is checked either way
+        let consumed = match program.next_code() {
+            Some(InstructionCode::Input) => program.inputs.first().copied(),
+            _ => None
+        };
+        let pc = program.pc();
+        let result = program.try_step();
+
+        let found = match result {
+            Ok(Some(ProgramResult::Output(n))) => Found::Output(n),
+            Ok(Some(ProgramResult::Complete)) if index == session.records.len() => return Ok(()),
+            Ok(Some(ProgramResult::Complete)) => Found::Complete,
+            Ok(None) => match consumed {
+                Some(n) => Found::Input(n),
+                None => continue
+            },
+            Err(error) => Found::Error(error)
+        };
+
+        let matches = match (expected, found) {
+            (Some(Record { event: Event::Input(n), step }), Found::Input(m)) => n == m && step.is_none_or(|s| s == program.steps()),
+            (Some(Record { event: Event::Output(n), step }), Found::Output(m)) => n == m && step.is_none_or(|s| s == program.steps()),
+            _ => false
+        };
+
+        if !matches {
+            return Err(Divergence { index, pc, steps: program.steps(), expected, found });
+        }
+
+        index += 1;
+    }
+}
+
+#[test]
+fn test_record_and_replay() {
+    let code = String::from("3,9,8,9,10,9,4,9,99,-1,8");
+
+    let mut recorder = Recorder::with_steps();
+    assert_eq!(Program::from(code.clone()).run_with(&mut vec!(8), &mut recorder), vec!(1));
+
+    let session: Session = recorder.finish().to_string().parse().unwrap();
+    assert_eq!(session.inputs(), vec!(8));
+    assert_eq!(session.outputs(), vec!(1));
+    assert_eq!(replay(&mut Program::from(code), &session), Ok(()));
+}
+
+#[test]
+fn test_replay_divergence() {
+    let code = String::from("3,9,8,9,10,9,4,9,99,-1,8");
+    let session: Session = "in 7\nout 1 4\n".parse().unwrap();
+
+    let divergence = replay(&mut Program::from(code), &session).unwrap_err();
+    assert_eq!(divergence.index, 1);
+    assert_eq!(divergence.pc, 6);
+    assert_eq!(divergence.steps, 3);
+    assert_eq!(divergence.found, Found::Output(0));
+}
+
+#[test]
+fn test_replay_reports_errors() {
+    let session: Session = "out 1\n".parse().unwrap();
+
+    let divergence = replay(&mut Program::from(vec!(42)), &session).unwrap_err();
+    assert_eq!(divergence.index, 0);
+    assert_eq!(divergence.found, Found::Error(ProgramError::InvalidOpcode { pc: 0, value: 42 }));
+}
+
+#[test]
+fn test_replay_checks_queued_inputs() {
+    let code = String::from("3,9,8,9,10,9,4,9,99,-1,8");
+    let session: Session = "in 8\nout 1\n".parse().unwrap();
+
+    let mut program = Program::from(code);
+    program.append_inputs(&mut vec!(7));
+    let divergence = replay(&mut program, &session).unwrap_err();
+    assert_eq!((divergence.index, divergence.found), (0, Found::Input(7)));
+
+    assert_eq!("in 8 -3\n".parse::<Session>(), Err(String::from("line 1: invalid step '-3'")));
+}
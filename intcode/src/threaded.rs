@@ -0,0 +1,115 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use crate::{Program, ProgramError, ProgramResult, Word};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExitStatus {
+    Complete,
+    InputClosed { pc: usize },
+    Error(ProgramError)
+}
+
+#[derive(Debug)]
+pub struct Halted {
+    pub memory: Vec<Word>,
+    pub steps: u64,
+    pub status: ExitStatus
+}
+
+pub struct Machine {
+    pub input: Sender<Word>,
+    pub output: Receiver<Word>,
+    handle: JoinHandle<Halted>
+}
+
+impl Machine {
+    pub fn join(self) -> thread::Result<Halted> {
+        self.handle.join()
+    }
+}
+
+pub fn spawn(program: Program) -> Machine {
+    let (input, inputs) = channel();
+    let (outputs, output) = channel();
+
+    Machine { input, output, handle: spawn_connected(program, inputs, outputs) }
+}
+
+// Runs the program on its own thread, blocking on `inputs` whenever it needs a value.
+// Outputs that nobody is listening for any more are dropped.
+pub fn spawn_connected(mut program: Program, inputs: Receiver<Word>, outputs: Sender<Word>) -> JoinHandle<Halted> {
+    thread::spawn(move || {
+        let status = loop {
+            if program.awaiting_input() {
+                match inputs.recv() {
+                    Ok(value) => program.inputs.push(value),
+                    Err(_) => break ExitStatus::InputClosed { pc: program.pc() }
+                }
+            }
+
+            match program.try_step() {
+                Ok(Some(ProgramResult::Output(value))) => { let _ = outputs.send(value); },
+                Ok(Some(ProgramResult::Complete)) => break ExitStatus::Complete,
+                Ok(None) => (),
+                Err(error) => break ExitStatus::Error(error)
+            }
+        };
+
+        Halted { steps: program.steps(), memory: program.memory, status }
+    })
+}
+
+#[test]
+fn test_spawn() {
+    let machine = spawn(Program::from(String::from("3,9,8,9,10,9,4,9,99,-1,8")));
+    machine.input.send(8).unwrap();
+
+    assert_eq!(machine.output.recv(), Ok(1));
+
+    let halted = machine.join().unwrap();
+    assert_eq!(halted.status, ExitStatus::Complete);
+    assert_eq!(halted.memory[9], 1);
+}
+
+#[test]
+fn test_input_closed() {
+    let machine = spawn(Program::from(String::from("3,0,99")));
+    drop(machine.input);
+
+    let halted = machine.handle.join().unwrap();
+    assert_eq!(halted.status, ExitStatus::InputClosed { pc: 0 });
+}
+
+#[test]
+fn test_feedback_ring() {
+    let code = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+    let phase_settings = [9, 8, 7, 6, 5];
+
+    // Each amplifier feeds the next; the last one feeds back through this thread
+    let (first_input, mut previous) = channel();
+    first_input.send(phase_settings[0]).unwrap();
+    first_input.send(0).unwrap();
+
+    let mut handles = Vec::new();
+    for n in 0..phase_settings.len() {
+        let (sender, receiver) = channel();
+        if let Some(phase) = phase_settings.get(n + 1) {
+            sender.send(*phase).unwrap();
+        }
+
+        handles.push(spawn_connected(Program::from(String::from(code)), previous, sender));
+        previous = receiver;
+    }
+
+    let mut last = 0;
+    for value in previous {
+        last = value;
+        let _ = first_input.send(value);
+    }
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap().status, ExitStatus::Complete);
+    }
+
+    assert_eq!(last, 139629729);
+}
@@ -0,0 +1,12 @@
+#![cfg(feature = "std")]
+
+use intcode::conformance::run_dir;
+
+#[test]
+fn test_vectors() {
+    let outcomes = run_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/vectors")).unwrap();
+    let failures: Vec<String> = outcomes.iter().filter(|o| o.result.is_err()).map(|o| o.to_string()).collect();
+
+    assert!(!outcomes.is_empty());
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,19 @@
+#![no_std]
+
+use intcode::{Program, ProgramError, ProgramResult};
+
+#[test]
+fn test_fixed_memory() {
+    let mut buffer = [-1; 32];
+    let mut program = Program::with_memory(&mut buffer[..], &[1101, 20, 22, 30, 4, 30, 99]).unwrap();
+
+    assert_eq!(program.run_until_event(), ProgramResult::Output(42));
+    assert_eq!(program.run_until_event(), ProgramResult::Complete);
+    assert_eq!(program.memory()[29], 0);
+}
+
+#[test]
+fn test_image_too_large() {
+    let result = Program::with_memory([0; 2], &[104, 1, 99]);
+    assert_eq!(result.err(), Some(ProgramError::ImageTooLarge { len: 3, capacity: 2 }));
+}